@@ -1,6 +1,31 @@
 mod scene;
 
-fn main() {
+use voxel::{action, camera, fly_camera, input};
+
+fn main() -> voxel::Result<()> {
     let game = scene::Scene::new();
-    voxel::run(game);
+    let camera = camera::Camera::new((0.0, 5.0, 10.0), cgmath::Deg(-90.0), cgmath::Deg(-20.0));
+    let controller = fly_camera::FlyCameraController::new(10.0, 0.004);
+    let bindings = action::Bindings::load(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/examples/ecs/bindings.ron"
+    ))?;
+
+    let mut builder = voxel::AppBuilder::new()
+        .with_profiler_overlay(true)
+        .with_plugin(input::InputPlugin::new(bindings))
+        .with_plugin(camera::CameraPlugin::new(camera, controller));
+    // VOXEL_TRACE=trace.json saves the last frames for chrome://tracing
+    if let Ok(path) = std::env::var("VOXEL_TRACE") {
+        builder = builder.with_profiler_trace(path);
+    }
+    // VOXEL_RECORD=session.ron records the input, VOXEL_REPLAY=session.ron replays it
+    if let Ok(path) = std::env::var("VOXEL_RECORD") {
+        builder = builder.with_recording(path);
+    }
+    if let Ok(path) = std::env::var("VOXEL_REPLAY") {
+        builder = builder.with_playback(path);
+    }
+
+    builder.run(game)
 }
@@ -1,14 +1,18 @@
 use cgmath::Rotation3;
 use core::time;
-use rand::{self, Rng};
+use rand::Rng;
 use specs::prelude::*;
 use voxel::{
     self,
+    action::Actions,
     actor::{self, transform},
-    camera::{self, CameraController},
-    delta_time::{self, DeltaTime},
-    event::{self, WinEvent},
-    fly_camera, scene, state,
+    camera, camera_path, cursor,
+    delta_time::DeltaTime,
+    input::InputState,
+    orbit_camera, physics, picking,
+    profiler::SystemsBuilder,
+    rng::GameRng,
+    scene, state, timer,
 };
 
 pub struct Scene {}
@@ -22,35 +26,45 @@ impl Scene {
 impl scene::Scene for Scene {
     fn setup_systems<'a, 'b>(
         &mut self,
-        dispatcher_builder: DispatcherBuilder<'a, 'b>,
-    ) -> DispatcherBuilder<'a, 'b> {
+        dispatcher_builder: SystemsBuilder<'a, 'b>,
+    ) -> SystemsBuilder<'a, 'b> {
         dispatcher_builder
             .with(AutoMovementSys {}, "auto_movement_sys", &[])
             .with(SpawnerSys, "spawner_sys", &[])
-            .with(CameraSys {}, "camera_sys", &[])
+            .with(CameraSwitchSys::default(), "camera_switch_sys", &[])
+            .with(PickSys, "pick_sys", &[])
     }
 
-    fn setup(&mut self, global_state: &mut state::State) {
+    fn setup(&mut self, global_state: &mut state::State) -> voxel::Result<()> {
+        global_state
+            .world
+            .write_resource::<cursor::Cursor>()
+            .set_relative(true);
         global_state.world.register::<Vel>();
-        global_state.world.register::<actor::Actor>();
-
-        global_state.world.insert(event::WinEvents::default());
-
-        let camera = camera::Camera::new((0.0, 5.0, 10.0), cgmath::Deg(-90.0), cgmath::Deg(-20.0));
-        let projection = camera::Projection::new(
-            global_state.render.size.0,
-            global_state.render.size.1,
-            cgmath::Deg(45.0),
-            0.1,
-            100.0,
-        );
-        let controller = fly_camera::FlyCameraController::new(500.0, 7.0);
-        global_state.world.insert(camera::CameraBundle::from_camera(
-            camera, projection, controller,
-        ));
-
-        global_state.world.insert(DeltaTime::default());
-        global_state.world.insert(delta_time::now());
+        global_state.world.register::<Spawner>();
+        global_state
+            .world
+            .create_entity()
+            .with(Spawner)
+            .with(timer::Timer::repeating(time::Duration::from_secs(1)))
+            .build();
+        // VOXEL_SPLIT=1 shows an overview camera on the right half of the window
+        if std::env::var("VOXEL_SPLIT").is_ok() {
+            global_state
+                .world
+                .write_resource::<camera::CameraBundle>()
+                .viewport = camera::Viewport::columns(0, 2);
+            let overview = camera::CameraBundle::from_camera(
+                camera::Camera::new((0.0, 25.0, 25.0), cgmath::Deg(-90.0), cgmath::Deg(-45.0)),
+                camera::Projection::default(),
+                Box::new(camera::StaticCameraController),
+            );
+            global_state
+                .world
+                .create_entity()
+                .with(overview.with_viewport(camera::Viewport::columns(1, 2)))
+                .build();
+        }
 
         // Spawn entities
         {
@@ -72,7 +86,7 @@ impl scene::Scene for Scene {
                     },
                     "/res/cube.obj",
                     Some([0.7, 0.3, 0.3, 1.0]),
-                ))
+                )?)
                 .build();
 
             global_state
@@ -93,7 +107,7 @@ impl scene::Scene for Scene {
                     },
                     "/res/cube.obj",
                     Some([0.3, 0.7, 0.3, 1.0]),
-                ))
+                )?)
                 .build();
 
             global_state
@@ -114,9 +128,11 @@ impl scene::Scene for Scene {
                     },
                     "/res/cube.obj",
                     Some([0.3, 0.3, 0.7, 1.0]),
-                ))
+                )?)
                 .build();
         }
+
+        Ok(())
     }
 }
 
@@ -145,22 +161,11 @@ impl<'a> System<'a> for AutoMovementSys {
     }
 }
 
-struct CameraSys {}
-
-impl<'a> System<'a> for CameraSys {
-    type SystemData = (Write<'a, camera::CameraBundle>, Read<'a, event::WinEvents>);
+#[derive(Debug, Default)]
+struct Spawner;
 
-    fn run(&mut self, (mut camera, events): Self::SystemData) {
-        log::trace!("running cameraSys system");
-
-        for ev in &events.events {
-            camera.controller.process_events(ev);
-            match ev {
-                WinEvent::Resize(w, h) => camera.projection.resize((*w, *h)),
-                _ => {}
-            }
-        }
-    }
+impl Component for Spawner {
+    type Storage = NullStorage<Self>;
 }
 
 struct SpawnerSys;
@@ -169,40 +174,147 @@ impl<'a> System<'a> for SpawnerSys {
     type SystemData = (
         Entities<'a>,
         Read<'a, LazyUpdate>,
-        Write<'a, time::Duration>
+        Write<'a, GameRng>,
+        ReadStorage<'a, Spawner>,
+        ReadStorage<'a, timer::Timer>,
     );
 
-    fn run(&mut self, (entites, updater, mut last_time): Self::SystemData) {
+    fn run(&mut self, (entites, updater, mut r, spawners, timers): Self::SystemData) {
         log::trace!("running SpawnerSys system");
 
-        let now = delta_time::now();
-        let dt = now - *last_time;
-        if dt.as_secs_f32() > 1.0 {
-            *last_time = now;
-            let mut r = rand::thread_rng();
-
-            let (x, y, z): (f32, f32, f32) = (r.gen_range(-5.0..5.0), r.gen_range(-5.0..5.0), r.gen_range(-5.0..5.0));
+        let spawns: u32 = (&spawners, &timers)
+            .join()
+            .map(|(_, timer)| timer.times_finished())
+            .sum();
+        for _ in 0..spawns {
+            let (x, y, z): (f32, f32, f32) = (
+                r.gen_range(-5.0..5.0),
+                r.gen_range(-5.0..5.0),
+                r.gen_range(-5.0..5.0),
+            );
             let (red, green, blue): (f32, f32, f32) = r.gen();
 
             let degree: f32 = r.gen();
             let vel = r.gen_range(-2.0..2.0);
 
-            let entity = entites.create();
-            updater.insert(entity, Vel(vel));
-            updater.insert(
-                entity,
-                actor::Actor::new(
-                    transform::Transform {
-                        position: cgmath::Vector3 { x: x * 10.0, y: y * 10.0, z: z * 10.0 },
-                        rotation: cgmath::Quaternion::from_axis_angle(
-                            cgmath::Vector3::unit_z(),
-                            cgmath::Deg(degree * 360.0),
-                        ),
+            let actor = actor::Actor::new(
+                transform::Transform {
+                    position: cgmath::Vector3 {
+                        x: x * 10.0,
+                        y: y * 10.0,
+                        z: z * 10.0,
                     },
-                    "/res/cube.obj",
-                    Some([red, green, blue, 1.0]),
-                ),
+                    rotation: cgmath::Quaternion::from_axis_angle(
+                        cgmath::Vector3::unit_z(),
+                        cgmath::Deg(degree * 360.0),
+                    ),
+                },
+                "/res/cube.obj",
+                Some([red, green, blue, 1.0]),
             );
+            match actor {
+                Ok(actor) => {
+                    let entity = entites.create();
+                    updater.insert(entity, Vel(vel));
+                    updater.insert(entity, actor);
+                }
+                Err(err) => log::error!("failed to spawn cube: {}", err),
+            }
+        }
+    }
+}
+
+/// Swaps between the fly camera and an orbit camera around the origin,
+/// and flies around the cubes on "play_path".
+struct CameraSwitchSys {
+    spare: camera::BoxedController,
+    flythrough: camera_path::CameraPath,
+}
+
+fn flythrough() -> camera_path::CameraPath {
+    use camera_path::{Keyframe, Look};
+
+    let center = Look::At((0.0, 0.0, 0.0).into());
+    camera_path::CameraPath::new("flythrough")
+        .with_keyframe(Keyframe::new(0.0, (0.0, 10.0, 30.0), center))
+        .with_keyframe(Keyframe::new(2.0, (30.0, 5.0, 0.0), center).with_fovy(cgmath::Deg(60.0)))
+        .with_keyframe(Keyframe::new(4.0, (0.0, 20.0, -30.0), center))
+        .with_keyframe(Keyframe::new(6.0, (-30.0, 5.0, 0.0), center).with_fovy(cgmath::Deg(30.0)))
+        .with_keyframe(Keyframe::new(8.0, (0.0, 10.0, 30.0), center).with_fovy(cgmath::Deg(45.0)))
+        .with_easing(camera_path::Easing::EaseInOut)
+}
+
+impl Default for CameraSwitchSys {
+    fn default() -> Self {
+        Self {
+            spare: Box::new(orbit_camera::OrbitCameraController::new(
+                (0.0, 0.0, 0.0),
+                15.0,
+                0.004,
+            )),
+            flythrough: flythrough(),
+        }
+    }
+}
+
+impl<'a> System<'a> for CameraSwitchSys {
+    type SystemData = (Read<'a, Actions>, Write<'a, camera::CameraBundle>);
+
+    fn run(&mut self, (actions, mut camera): Self::SystemData) {
+        log::trace!("running CameraSwitchSys system");
+
+        if actions.just_pressed("switch_camera") {
+            std::mem::swap(&mut self.spare, &mut camera.controller);
+        }
+        if actions.just_pressed("play_path") {
+            camera.play_path(self.flythrough.clone());
+        }
+    }
+}
+
+/// Logs the actor under the cursor on "pick".
+struct PickSys;
+
+impl<'a> System<'a> for PickSys {
+    type SystemData = (
+        Read<'a, Actions>,
+        Read<'a, InputState>,
+        Read<'a, camera::CameraBundle>,
+        Entities<'a>,
+        ReadStorage<'a, actor::Actor>,
+        ReadStorage<'a, physics::RigidBodyComponent>,
+        Option<Read<'a, physics::Physics>>,
+    );
+
+    fn run(
+        &mut self,
+        (actions, input, camera, entities, actors, rigid_bodies, physics): Self::SystemData,
+    ) {
+        log::trace!("running PickSys system");
+
+        if !actions.just_pressed("pick") {
+            return;
+        }
+        let ray = input
+            .cursor_position()
+            .and_then(|cursor| camera.cursor_ray(cursor));
+        let ray = match ray {
+            Some(ray) => ray,
+            None => return,
+        };
+        let world = picking::PickWorld {
+            entities: &entities,
+            actors: &actors,
+            rigid_bodies: &rigid_bodies,
+            physics: physics.as_deref(),
+        };
+        match world.pick(&ray, 100.0) {
+            Some(picking::Hit {
+                entity: Some(entity),
+                point,
+                ..
+            }) => log::info!("picked {:?} at {:?}", entity, point),
+            _ => log::info!("nothing picked"),
         }
     }
 }
@@ -1,6 +1,17 @@
 mod scene;
 
-fn main() {
+use voxel::{camera, fly_camera, input, physics};
+
+fn main() -> voxel::Result<()> {
     let game = scene::Scene::new();
-    voxel::run(game);
+    let camera = camera::Camera::new((0.0, 15.0, 20.0), cgmath::Deg(-90.0), cgmath::Deg(-20.0));
+    let controller = fly_camera::FlyCameraController::new(10.0, 0.004);
+
+    voxel::AppBuilder::new()
+        .with_title("voxel - rapier")
+        .with_size(1280, 720)
+        .with_plugin(input::InputPlugin::default())
+        .with_plugin(camera::CameraPlugin::new(camera, controller))
+        .with_plugin(physics::PhysicsPlugin)
+        .run(game)
 }
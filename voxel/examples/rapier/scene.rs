@@ -1,15 +1,16 @@
 use cgmath::Rotation3;
-use rand::{self, Rng};
+use rand::Rng;
 use rapier3d::prelude::*;
 use specs::prelude::*;
-use std::fmt::Debug;
 use voxel::{
     self,
     actor::{self, transform},
-    camera::{self, CameraController},
-    delta_time::{self, DeltaTime},
-    event::{self, WinEvent},
-    fly_camera, physics, scene, state,
+    camera, cursor,
+    delta_time::DeltaTime,
+    follow_camera, physics,
+    profiler::SystemsBuilder,
+    rng::GameRng,
+    scene, state,
 };
 
 pub struct Scene {}
@@ -23,36 +24,17 @@ impl Scene {
 impl scene::Scene for Scene {
     fn setup_systems<'a, 'b>(
         &mut self,
-        dispatcher_builder: DispatcherBuilder<'a, 'b>,
-    ) -> DispatcherBuilder<'a, 'b> {
-        dispatcher_builder
-            .with(AutoMovementSys {}, "auto_movement_sys", &[])
-            .with(CameraSys {}, "camera_sys", &[])
-            .with(PhysicsSys {}, "physics_scenario_sys", &[])
+        dispatcher_builder: SystemsBuilder<'a, 'b>,
+    ) -> SystemsBuilder<'a, 'b> {
+        dispatcher_builder.with(AutoMovementSys {}, "auto_movement_sys", &[])
     }
 
-    fn setup(&mut self, global_state: &mut state::State) {
+    fn setup(&mut self, global_state: &mut state::State) -> voxel::Result<()> {
+        global_state
+            .world
+            .write_resource::<cursor::Cursor>()
+            .set_relative(true);
         global_state.world.register::<Vel>();
-        global_state.world.register::<actor::Actor>();
-        global_state.world.register::<physics::RigidBodyComponent>();
-
-        global_state.world.insert(event::WinEvents::default());
-
-        let camera = camera::Camera::new((0.0, 15.0, 20.0), cgmath::Deg(-90.0), cgmath::Deg(-20.0));
-        let projection = camera::Projection::new(
-            global_state.render.size.0,
-            global_state.render.size.1,
-            cgmath::Deg(45.0),
-            0.1,
-            100.0,
-        );
-        let controller = fly_camera::FlyCameraController::new(500.0, 7.0);
-        global_state.world.insert(camera::CameraBundle::from_camera(
-            camera, projection, controller,
-        ));
-
-        global_state.world.insert(DeltaTime::default());
-        global_state.world.insert(delta_time::now());
 
         // Spawn entities
         {
@@ -70,9 +52,9 @@ impl scene::Scene for Scene {
                 },
                 "/res/cube.obj",
                 Some([0.7, 0.3, 0.3, 1.0]),
-            );
+            )?;
 
-            let mut physics_eng = Physics::new();
+            let mut physics_eng = physics::Physics::new();
             init_scenario(&mut physics_eng);
 
             /* Create the bounding cube. */
@@ -116,7 +98,7 @@ impl scene::Scene for Scene {
                 },
                 "/res/cube.obj",
                 Some([0.3, 0.7, 0.3, 1.0]),
-            );
+            )?;
             let rigid_body = RigidBodyBuilder::dynamic()
                 .translation(vector![
                     second_cube.transform.position.x,
@@ -156,7 +138,7 @@ impl scene::Scene for Scene {
                 },
                 "/res/cube.obj",
                 Some([0.3, 0.3, 0.7, 1.0]),
-            );
+            )?;
             let rigid_body = RigidBodyBuilder::dynamic()
                 .translation(vector![
                     third_cube.transform.position.x,
@@ -174,24 +156,35 @@ impl scene::Scene for Scene {
                 &mut physics_eng.rigid_body_set,
             );
 
-            global_state
+            let followed = global_state
                 .world
                 .create_entity()
                 .with(Vel(0.05))
                 .with(third_cube)
                 .with(physics::RigidBodyComponent::new(body_handle))
                 .build();
+            if let Some(mut camera) = global_state.world.try_fetch_mut::<camera::CameraBundle>() {
+                camera.set_controller(follow_camera::FollowCameraController::new(
+                    followed,
+                    (0.0, 4.0, 12.0),
+                ));
+            }
 
             /* Create the bounding cube. */
             for _ in 0..100 {
-                let mut r = rand::thread_rng();
-
-                let (x, y, z): (f32, f32, f32) = (
-                    r.gen_range(-20.0..20.0),
-                    r.gen_range(5.0..100.0),
-                    r.gen_range(-20.0..20.0),
-                );
-                let (red, green, blue): (f32, f32, f32) = r.gen();
+                // seeded, so replays spawn the same cubes
+                let ((x, y, z), (red, green, blue), restitution) = {
+                    let mut r = global_state.world.write_resource::<GameRng>();
+                    (
+                        (
+                            r.gen_range(-20.0..20.0),
+                            r.gen_range(5.0..100.0),
+                            r.gen_range(-20.0..20.0),
+                        ),
+                        r.gen(),
+                        r.gen_range(0.4..1.0),
+                    )
+                };
                 let cube_actor = actor::Actor::new(
                     transform::Transform {
                         position: cgmath::Vector3 { x, y, z },
@@ -202,7 +195,7 @@ impl scene::Scene for Scene {
                     },
                     "/res/cube.obj",
                     Some([red, green, blue, 1.0]),
-                );
+                )?;
                 let rigid_body = RigidBodyBuilder::dynamic()
                     .translation(vector![
                         cube_actor.transform.position.x,
@@ -211,7 +204,7 @@ impl scene::Scene for Scene {
                     ])
                     .build();
                 let collider = ColliderBuilder::cuboid(1.0, 1.0, 1.0)
-                    .restitution(r.gen_range(0.4..1.0))
+                    .restitution(restitution)
                     .build();
                 let body_handle = physics_eng.rigid_body_set.insert(rigid_body);
                 physics_eng.collider_set.insert_with_parent(
@@ -231,6 +224,8 @@ impl scene::Scene for Scene {
 
             global_state.world.insert(physics_eng);
         }
+
+        Ok(())
     }
 }
 
@@ -259,118 +254,7 @@ impl<'a> System<'a> for AutoMovementSys {
     }
 }
 
-struct CameraSys {}
-
-impl<'a> System<'a> for CameraSys {
-    type SystemData = (Write<'a, camera::CameraBundle>, Read<'a, event::WinEvents>);
-
-    fn run(&mut self, (mut camera, events): Self::SystemData) {
-        log::trace!("running cameraSys system");
-
-        for ev in &events.events {
-            camera.controller.process_events(ev);
-            match ev {
-                WinEvent::Resize(w, h) => camera.projection.resize((*w, *h)),
-                _ => {}
-            }
-        }
-    }
-}
-
-struct Physics {
-    pipeline: rapier3d::prelude::PhysicsPipeline,
-    rigid_body_set: RigidBodySet,
-    collider_set: ColliderSet,
-    integration_parameters: IntegrationParameters,
-    island_manager: IslandManager,
-    broad_phase: BroadPhase,
-    narrow_phase: NarrowPhase,
-    impulse_joint_set: ImpulseJointSet,
-    multibody_joint_set: MultibodyJointSet,
-    ccd_solver: CCDSolver,
-    gravity: nalgebra::Vector3<f32>,
-    physics_hooks: (),
-    event_handler: (),
-}
-
-impl Physics {
-    fn new() -> Self {
-        Self {
-            pipeline: Default::default(),
-            rigid_body_set: RigidBodySet::new(),
-            collider_set: ColliderSet::new(),
-            gravity: vector![0.0, -9.81, 0.0],
-            integration_parameters: IntegrationParameters::default(),
-            island_manager: IslandManager::new(),
-            broad_phase: BroadPhase::new(),
-            narrow_phase: NarrowPhase::new(),
-            impulse_joint_set: ImpulseJointSet::new(),
-            multibody_joint_set: MultibodyJointSet::new(),
-            ccd_solver: CCDSolver::new(),
-            physics_hooks: (),
-            event_handler: (),
-        }
-    }
-
-    fn step(&mut self) {
-        self.pipeline.step(
-            &self.gravity,
-            &self.integration_parameters,
-            &mut self.island_manager,
-            &mut self.broad_phase,
-            &mut self.narrow_phase,
-            &mut self.rigid_body_set,
-            &mut self.collider_set,
-            &mut self.impulse_joint_set,
-            &mut self.multibody_joint_set,
-            &mut self.ccd_solver,
-            None,
-            &self.physics_hooks,
-            &self.event_handler,
-        );
-    }
-}
-
-impl Default for Physics {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
-struct PhysicsSys {}
-
-impl<'a> System<'a> for PhysicsSys {
-    type SystemData = (
-        Write<'a, Physics>,
-        WriteStorage<'a, actor::Actor>,
-        ReadStorage<'a, physics::RigidBodyComponent>,
-    );
-
-    fn run(&mut self, (mut physics, mut actors, rbs): Self::SystemData) {
-        log::trace!("running Physics system");
-        physics.step();
-        // update actors' transform
-        for (actor, rb) in (&mut actors, &rbs).join() {
-            let actor_body = &physics.rigid_body_set.get(rb.rigid_body);
-            if let None = *actor_body {
-                continue;
-            }
-            let actor_body = actor_body.unwrap();
-            actor.transform.position.x = actor_body.translation().x;
-            actor.transform.position.y = actor_body.translation().y;
-            actor.transform.position.z = actor_body.translation().z;
-
-            actor.transform.rotation.s = actor_body.rotation().scalar();
-            actor.transform.rotation.v = cgmath::Vector3::from((
-                actor_body.rotation().vector()[0],
-                actor_body.rotation().vector()[1],
-                actor_body.rotation().vector()[2],
-            ));
-        }
-    }
-}
-
-fn init_scenario(physics: &mut Physics) {
+fn init_scenario(physics: &mut physics::Physics) {
     /* Create the ground. */
     let collider = ColliderBuilder::cuboid(100.0, 0.1, 100.0).build();
     physics.collider_set.insert(collider);
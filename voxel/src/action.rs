@@ -0,0 +1,223 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error;
+use crate::event::{Key, MouseButton};
+use crate::input::InputState;
+use crate::ron_file;
+
+/// A key or mouse button an action can be bound to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Binding {
+    Key(Key),
+    Mouse(MouseButton),
+}
+
+impl Binding {
+    fn is_pressed(&self, input: &InputState) -> bool {
+        match self {
+            Binding::Key(key) => input.is_pressed(*key),
+            Binding::Mouse(button) => input.is_mouse_pressed(*button),
+        }
+    }
+
+    fn just_pressed(&self, input: &InputState) -> bool {
+        match self {
+            Binding::Key(key) => input.just_pressed(*key),
+            Binding::Mouse(button) => input.mouse_just_pressed(*button),
+        }
+    }
+}
+
+/// Source of an axis value, the values of every binding of an axis are summed.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum AxisBinding {
+    /// -1 while `negative` is held, 1 while `positive` is held.
+    Buttons {
+        negative: Binding,
+        positive: Binding,
+    },
+    /// Raw mouse motion since the previous step, multiplied by the scale.
+    MouseX(f32),
+    MouseY(f32),
+    /// Wheel lines since the previous step, multiplied by the scale.
+    Scroll(f32),
+}
+
+impl AxisBinding {
+    fn value(&self, input: &InputState) -> f32 {
+        match self {
+            AxisBinding::Buttons { negative, positive } => {
+                positive.is_pressed(input) as i32 as f32 - negative.is_pressed(input) as i32 as f32
+            }
+            AxisBinding::MouseX(scale) => input.mouse_delta().0 as f32 * scale,
+            AxisBinding::MouseY(scale) => input.mouse_delta().1 as f32 * scale,
+            AxisBinding::Scroll(scale) => input.scroll_delta() as f32 * scale,
+        }
+    }
+}
+
+/// Named actions and axes with their bindings, as stored in a RON file:
+///
+/// ```ron
+/// (
+///     actions: {
+///         "jump": [Key(Space)],
+///         "fire": [Mouse(Left), Key(LControl)],
+///     },
+///     axes: {
+///         "look_x": [MouseX(1.0)],
+///     },
+/// )
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Bindings {
+    #[serde(default)]
+    pub actions: HashMap<String, Vec<Binding>>,
+    #[serde(default)]
+    pub axes: HashMap<String, Vec<AxisBinding>>,
+}
+
+impl Default for Bindings {
+    /// Bindings the fly camera expects: WASD or arrows to move,
+    /// Space and LShift to go up and down, the mouse to look around.
+    fn default() -> Self {
+        let mut bindings = Self::empty();
+        bindings.bind("move_forward", Binding::Key(Key::W));
+        bindings.bind("move_forward", Binding::Key(Key::Up));
+        bindings.bind("move_backward", Binding::Key(Key::S));
+        bindings.bind("move_backward", Binding::Key(Key::Down));
+        bindings.bind("move_left", Binding::Key(Key::A));
+        bindings.bind("move_left", Binding::Key(Key::Left));
+        bindings.bind("move_right", Binding::Key(Key::D));
+        bindings.bind("move_right", Binding::Key(Key::Right));
+        bindings.bind("move_up", Binding::Key(Key::Space));
+        bindings.bind("move_down", Binding::Key(Key::LShift));
+        bindings.bind_axis("look_x", AxisBinding::MouseX(1.0));
+        bindings.bind_axis("look_y", AxisBinding::MouseY(1.0));
+        bindings.bind_axis("zoom", AxisBinding::Scroll(1.0));
+        bindings
+    }
+}
+
+impl Bindings {
+    pub fn empty() -> Self {
+        Self {
+            actions: HashMap::new(),
+            axes: HashMap::new(),
+        }
+    }
+
+    pub fn from_ron(src: &str) -> Result<Self, ron::error::SpannedError> {
+        ron::from_str(src)
+    }
+
+    pub fn to_ron(&self) -> Result<String, ron::Error> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> error::Result<Self> {
+        ron_file::load(path)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> error::Result<()> {
+        ron_file::save(path, self)
+    }
+
+    pub fn bind(&mut self, action: &str, binding: Binding) {
+        let bindings = self.actions.entry(action.to_string()).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    pub fn unbind(&mut self, action: &str, binding: Binding) {
+        if let Some(bindings) = self.actions.get_mut(action) {
+            bindings.retain(|b| *b != binding);
+        }
+    }
+
+    pub fn bind_axis(&mut self, axis: &str, binding: AxisBinding) {
+        self.axes.entry(axis.to_string()).or_default().push(binding);
+    }
+
+    /// Removes every binding of an action or axis.
+    pub fn clear(&mut self, name: &str) {
+        self.actions.remove(name);
+        self.axes.remove(name);
+    }
+}
+
+/// Action and axis values, updated from `InputState` by the game ticker before every step.
+///
+/// Bindings can be changed at any time, the new ones apply from the next step.
+#[derive(Default)]
+pub struct Actions {
+    pub bindings: Bindings,
+    pressed: HashSet<String>,
+    just_pressed: HashSet<String>,
+    just_released: HashSet<String>,
+    axes: HashMap<String, f32>,
+}
+
+impl Actions {
+    pub fn new(bindings: Bindings) -> Self {
+        Self {
+            bindings,
+            ..Default::default()
+        }
+    }
+
+    pub fn update(&mut self, input: &InputState) {
+        self.just_pressed.clear();
+        self.just_released.clear();
+
+        for (action, bindings) in &self.bindings.actions {
+            let was_pressed = self.pressed.contains(action);
+            let is_pressed = bindings.iter().any(|b| b.is_pressed(input));
+            // pressed and released within the same step
+            let tapped = !was_pressed && bindings.iter().any(|b| b.just_pressed(input));
+
+            if is_pressed {
+                self.pressed.insert(action.clone());
+            } else {
+                self.pressed.remove(action);
+            }
+            if (!was_pressed && is_pressed) || tapped {
+                self.just_pressed.insert(action.clone());
+            }
+            if (was_pressed || tapped) && !is_pressed {
+                self.just_released.insert(action.clone());
+            }
+        }
+        // actions unbound while pressed
+        let bindings = &self.bindings;
+        self.pressed
+            .retain(|action| bindings.actions.contains_key(action));
+
+        self.axes.clear();
+        for (axis, bindings) in &self.bindings.axes {
+            let value = bindings.iter().map(|b| b.value(input)).sum();
+            self.axes.insert(axis.clone(), value);
+        }
+    }
+
+    pub fn is_pressed(&self, action: &str) -> bool {
+        self.pressed.contains(action)
+    }
+
+    pub fn just_pressed(&self, action: &str) -> bool {
+        self.just_pressed.contains(action)
+    }
+
+    pub fn just_released(&self, action: &str) -> bool {
+        self.just_released.contains(action)
+    }
+
+    /// 0 for unknown axes.
+    pub fn axis(&self, axis: &str) -> f32 {
+        self.axes.get(axis).copied().unwrap_or(0.0)
+    }
+}
@@ -1,6 +1,7 @@
 use super::model;
 use super::spawner;
 use super::transform;
+use crate::error;
 use specs::{Component, DenseVecStorage};
 
 #[derive(Clone, Component, Debug)]
@@ -11,7 +12,11 @@ pub struct Actor {
 }
 
 impl Actor {
-    pub fn new(transform: transform::Transform, obj_path: &str, color: Option<[f32; 4]>) -> Self {
+    pub fn new(
+        transform: transform::Transform,
+        obj_path: &str,
+        color: Option<[f32; 4]>,
+    ) -> error::Result<Self> {
         Self::new_with_name(String::new(), transform, obj_path, color)
     }
 
@@ -20,12 +25,12 @@ impl Actor {
         transform: transform::Transform,
         obj_path: &str,
         color: Option<[f32; 4]>,
-    ) -> Self {
-        let m = spawner::load_model(obj_path, color);
-        Self {
+    ) -> error::Result<Self> {
+        let m = spawner::load_model(obj_path, color)?;
+        Ok(Self {
             name,
             transform,
             model: m,
-        }
+        })
     }
 }
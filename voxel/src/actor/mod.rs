@@ -1,3 +1,4 @@
+#[allow(clippy::module_inception)]
 pub mod actor;
 pub mod model;
 pub mod resources;
@@ -1,3 +1,5 @@
+use crate::bounds;
+
 #[derive(Clone, Debug)]
 pub struct Model {
     pub mesh: Mesh,
@@ -9,6 +11,21 @@ pub struct Mesh {
     pub id: String,
     pub indices: Vec<u32>,
     pub vertices: Vec<MeshVertex>,
+    /// Box around the vertices in model space.
+    pub bounds: bounds::Aabb,
+}
+
+impl Mesh {
+    pub fn new(id: String, indices: Vec<u32>, vertices: Vec<MeshVertex>) -> Self {
+        let bounds = bounds::Aabb::from_points(vertices.iter().map(|v| v.position.into()));
+
+        Self {
+            id,
+            indices,
+            vertices,
+            bounds,
+        }
+    }
 }
 
 #[repr(C)]
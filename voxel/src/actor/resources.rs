@@ -17,13 +17,18 @@ pub fn load_model(
             ..Default::default()
         },
         |p| {
-            let mat_text = load_string(p.to_str().unwrap()).unwrap();
+            let mat_text = p
+                .to_str()
+                .and_then(|p| load_string(p).ok())
+                .ok_or(tobj::LoadError::OpenFileFailed)?;
             tobj::load_mtl_buf(&mut BufReader::new(Cursor::new(mat_text)))
         },
     )?;
 
     let mesh = {
-        let m = &models[0];
+        let m = models
+            .first()
+            .ok_or_else(|| anyhow::anyhow!("no model found in {}", file_name))?;
         // iterate of array with x,y,z vertice data aside
         let vertices = (0..m.mesh.positions.len() / 3)
             .map(|i| model::MeshVertex {
@@ -36,11 +41,7 @@ pub fn load_model(
             })
             .collect::<Vec<_>>();
 
-        model::Mesh {
-            id: file_name.to_string(),
-            indices: m.mesh.indices.clone(),
-            vertices,
-        }
+        model::Mesh::new(file_name.to_string(), m.mesh.indices.clone(), vertices)
     };
 
     Ok(model::Model { mesh, color })
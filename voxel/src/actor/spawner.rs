@@ -1,16 +1,23 @@
 use std::ops::Add;
 use super::{model, resources};
+use crate::error;
 
-pub fn push_model(models: &mut Vec<model::Model>, obj_path: &str, color: Option<[f32; 4]>) {
-    let m = load_model(obj_path, color);
+pub fn push_model(
+    models: &mut Vec<model::Model>,
+    obj_path: &str,
+    color: Option<[f32; 4]>,
+) -> error::Result<()> {
+    let m = load_model(obj_path, color)?;
 
     models.push(m);
+    Ok(())
 }
 
-pub fn load_model(obj_path: &str, color: Option<[f32; 4]>) -> model::Model {
+pub fn load_model(obj_path: &str, color: Option<[f32; 4]>) -> error::Result<model::Model> {
     log::debug!("loading model");
 
-    resources::load_model(&path_with_out_dir(obj_path), color).unwrap()
+    resources::load_model(&path_with_out_dir(obj_path), color)
+        .map_err(|err| error::Error::asset(obj_path, err))
 }
 
 fn path_with_out_dir(obj_path: &str) -> String {
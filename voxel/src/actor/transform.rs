@@ -5,3 +5,15 @@ pub struct Transform {
     pub position: cgmath::Vector3<f32>,
     pub rotation: cgmath::Quaternion<f32>,
 }
+
+impl Transform {
+    /// Blends towards `other`, `alpha` of 0.0 is `self` and 1.0 is `other`.
+    pub fn interpolate(&self, other: &Transform, alpha: f32) -> Transform {
+        use cgmath::VectorSpace;
+
+        Transform {
+            position: self.position.lerp(other.position, alpha),
+            rotation: self.rotation.nlerp(other.rotation, alpha),
+        }
+    }
+}
@@ -1,30 +1,258 @@
+use std::time;
+
+use crate::error;
 use crate::game_ticker;
+use crate::plugin;
+use crate::profiler;
+use crate::replay;
+use crate::ticker::Ticker;
+use specs::WorldExt;
 
-use super::renderer::window;
+use super::renderer::{render, window};
 use super::scene;
 use super::state;
 
 pub struct App {
     pub global_state: state::State,
     pub game_ticker: game_ticker::GameTicker,
-    window: winit::window::Window,
-    ev_loop: winit::event_loop::EventLoop<()>,
+    // None when running headless
+    window: Option<(winit::event_loop::EventLoop<()>, winit::window::Window)>,
+    // window title the profiler overlay is appended to
+    title: String,
 }
 
 impl App {
-    pub fn new(scene: Box<dyn scene::Scene>) -> Self {
-        env_logger::init();
-        let (ev_loop, window) = window::create_win();
-        let game_ticker = game_ticker::GameTicker{};
-        Self {
-            global_state: state::State::new(scene, &window),
+    pub fn new(scene: Box<dyn scene::Scene>) -> error::Result<Self> {
+        AppBuilder::new().build(scene)
+    }
+
+    /// Creates an app without window, surface or GPU device.
+    /// Scene systems are dispatched on every tick but nothing is rendered,
+    /// which is what simulation tests and dedicated servers need.
+    pub fn headless(scene: Box<dyn scene::Scene>) -> error::Result<Self> {
+        AppBuilder::new().with_headless(true).build(scene)
+    }
+
+    pub fn is_headless(&self) -> bool {
+        self.window.is_none()
+    }
+
+    /// Runs a single fixed simulation step.
+    pub fn tick(&mut self) -> error::Result<()> {
+        let result = self.game_ticker.step(&mut self.global_state);
+        self.global_state
+            .world
+            .write_resource::<profiler::Profiler>()
+            .end_frame();
+        if result.is_err() {
+            self.game_ticker.save_recording();
+        }
+
+        result
+    }
+
+    /// Runs until every scene is popped, the window is closed or an error occurs.
+    pub fn run(mut self) -> error::Result<()> {
+        match self.window {
+            Some((ev_loop, window)) => window::run(
+                ev_loop,
+                window,
+                &self.title,
+                self.game_ticker,
+                self.global_state,
+            ),
+            None => {
+                let mut result = Ok(());
+                while result.is_ok() && self.global_state.is_running() {
+                    result = self.game_ticker.tick(&mut self.global_state, Vec::new());
+                    std::thread::sleep(self.game_ticker.remaining());
+                }
+                if let Err(err) = &result {
+                    log::error!("game ticker failed: {}", err);
+                    self.game_ticker.save_recording();
+                }
+                self.game_ticker.finish(&self.global_state);
+
+                result
+            }
+        }
+    }
+}
+
+/// Configures the window, surface, systems thread pool and plugins before building an [`App`].
+#[derive(Default)]
+pub struct AppBuilder {
+    pub window: window::WindowConfig,
+    pub render: render::RenderConfig,
+    pub state: state::StateConfig,
+    pub fixed_step: Option<time::Duration>,
+    pub max_steps: Option<u32>,
+    pub headless: bool,
+    /// Where the input of the session is recorded to.
+    pub record: Option<std::path::PathBuf>,
+    /// Recording replayed in place of live input.
+    pub playback: Option<std::path::PathBuf>,
+    pub plugins: Vec<Box<dyn plugin::Plugin>>,
+}
+
+impl AppBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_title(mut self, title: &str) -> Self {
+        self.window.title = title.to_string();
+        self
+    }
+
+    pub fn with_size(mut self, width: u32, height: u32) -> Self {
+        self.window.size = (width, height);
+        self.state.headless_size = (width, height);
+        self
+    }
+
+    pub fn with_fullscreen(mut self, fullscreen: bool) -> Self {
+        self.window.fullscreen = fullscreen;
+        self
+    }
+
+    pub fn with_resizable(mut self, resizable: bool) -> Self {
+        self.window.resizable = resizable;
+        self
+    }
+
+    pub fn with_present_mode(mut self, present_mode: wgpu::PresentMode) -> Self {
+        self.render.present_mode = present_mode;
+        self
+    }
+
+    pub fn with_surface_format(mut self, format: wgpu::TextureFormat) -> Self {
+        self.render.surface_format = Some(format);
+        self
+    }
+
+    pub fn with_clear_color(mut self, color: wgpu::Color) -> Self {
+        self.render.clear_color = color;
+        self
+    }
+
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.state.threads = threads;
+        self
+    }
+
+    /// `build` fails if `fixed_step` is zero.
+    pub fn with_fixed_step(mut self, fixed_step: time::Duration) -> Self {
+        self.fixed_step = Some(fixed_step);
+        self
+    }
+
+    /// `build` fails if `max_steps` is zero, the simulation would never advance.
+    pub fn with_max_steps(mut self, max_steps: u32) -> Self {
+        self.max_steps = Some(max_steps);
+        self
+    }
+
+    /// Records engine phases and system runs, see [`profiler::Profiler`].
+    pub fn with_profiler(mut self, enabled: bool) -> Self {
+        self.state.profiler.enabled = enabled;
+        self
+    }
+
+    pub fn with_profiler_history(mut self, frames: usize) -> Self {
+        self.state.profiler.history = frames;
+        self
+    }
+
+    /// Saves a Chrome trace of the last frames once the app stops, enabling the profiler.
+    pub fn with_profiler_trace<P: Into<std::path::PathBuf>>(mut self, path: P) -> Self {
+        self.state.profiler.trace_path = Some(path.into());
+        self.state.profiler.enabled = true;
+        self
+    }
+
+    /// Shows the profiler summary in the window title, enabling the profiler.
+    pub fn with_profiler_overlay(mut self, overlay: bool) -> Self {
+        self.state.profiler.overlay = overlay;
+        self.state.profiler.enabled |= overlay;
+        self
+    }
+
+    /// Seeds the `GameRng` resource, a random seed is used otherwise.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.state.seed = Some(seed);
+        self
+    }
+
+    /// Records every tick's input to `path`, saved every few seconds and once the app stops.
+    pub fn with_recording<P: Into<std::path::PathBuf>>(mut self, path: P) -> Self {
+        self.record = Some(path.into());
+        self
+    }
+
+    /// Replays the recording at `path` in place of live input.
+    /// Its seed and step settings override the builder's.
+    pub fn with_playback<P: Into<std::path::PathBuf>>(mut self, path: P) -> Self {
+        self.playback = Some(path.into());
+        self
+    }
+
+    pub fn with_headless(mut self, headless: bool) -> Self {
+        self.headless = headless;
+        self
+    }
+
+    /// Plugins are set up in the order they are added.
+    pub fn with_plugin<P: plugin::Plugin + 'static>(mut self, plugin: P) -> Self {
+        self.plugins.push(Box::new(plugin));
+        self
+    }
+
+    pub fn build(mut self, scene: Box<dyn scene::Scene>) -> error::Result<App> {
+        let _ = env_logger::try_init();
+
+        let mut game_ticker = game_ticker::GameTicker::default();
+        if let Some(fixed_step) = self.fixed_step {
+            game_ticker.set_fixed_step(fixed_step)?;
+        }
+        if let Some(max_steps) = self.max_steps {
+            game_ticker.set_max_steps(max_steps)?;
+        }
+        if let Some(path) = &self.playback {
+            let recording = replay::Recording::load(path)?;
+            self.state.seed = Some(recording.seed);
+            game_ticker.play(replay::Player::new(recording))?;
+        }
+        let seed = *self.state.seed.get_or_insert_with(rand::random);
+        if let Some(path) = self.record {
+            let recording =
+                replay::Recording::new(seed, game_ticker.fixed_step(), game_ticker.max_steps());
+            game_ticker.record(replay::Recorder::new(recording, Some(path)));
+        }
+
+        let (render, window) = if self.headless {
+            (None, None)
+        } else {
+            let (ev_loop, window) = window::create_win(&self.window)?;
+            let render = render::Render::new(&window, &self.render)?;
+            (Some(render), Some((ev_loop, window)))
+        };
+
+        let mut global_state = state::State::with_config(render, &self.state)?;
+        for plugin in self.plugins {
+            global_state.add_plugin(plugin)?;
+        }
+        global_state.push_scene(scene)?;
+
+        Ok(App {
+            global_state,
             game_ticker,
-            ev_loop,
             window,
-        }
+            title: self.window.title,
+        })
     }
 
-    pub fn run(self) {        
-        window::run(self.ev_loop, self.window, self.game_ticker, self.global_state);
+    pub fn run(self, scene: Box<dyn scene::Scene>) -> error::Result<()> {
+        self.build(scene)?.run()
     }
 }
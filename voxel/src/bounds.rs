@@ -0,0 +1,351 @@
+use cgmath::{prelude::*, Matrix4, Point3, Vector3, Vector4};
+
+use crate::actor::transform;
+
+/// Axis aligned bounding box.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Point3<f32>,
+    pub max: Point3<f32>,
+}
+
+impl Default for Aabb {
+    fn default() -> Self {
+        Self {
+            min: Point3::origin(),
+            max: Point3::origin(),
+        }
+    }
+}
+
+impl Aabb {
+    pub fn new(min: Point3<f32>, max: Point3<f32>) -> Self {
+        Self { min, max }
+    }
+
+    /// Smallest box holding every point, an empty box at the origin without points.
+    pub fn from_points<I: IntoIterator<Item = Point3<f32>>>(points: I) -> Self {
+        let mut points = points.into_iter();
+        let first = match points.next() {
+            Some(point) => point,
+            None => return Self::default(),
+        };
+
+        points.fold(Self::new(first, first), |aabb, point| Self {
+            min: Point3::new(
+                aabb.min.x.min(point.x),
+                aabb.min.y.min(point.y),
+                aabb.min.z.min(point.z),
+            ),
+            max: Point3::new(
+                aabb.max.x.max(point.x),
+                aabb.max.y.max(point.y),
+                aabb.max.z.max(point.z),
+            ),
+        })
+    }
+
+    pub fn center(&self) -> Point3<f32> {
+        self.min.midpoint(self.max)
+    }
+
+    /// Half the size along each axis.
+    pub fn extents(&self) -> Vector3<f32> {
+        (self.max - self.min) / 2.0
+    }
+
+    /// Box holding this one once moved by `transform`.
+    pub fn transformed(&self, transform: &transform::Transform) -> Self {
+        let rotation = cgmath::Matrix3::from(transform.rotation);
+        let center = rotation * self.center().to_vec() + transform.position;
+        let extents = self.extents();
+        // each world axis gets the projection of every rotated local axis
+        let abs = |v: Vector3<f32>| Vector3::new(v.x.abs(), v.y.abs(), v.z.abs());
+        let world_extents =
+            abs(rotation.x) * extents.x + abs(rotation.y) * extents.y + abs(rotation.z) * extents.z;
+
+        Self {
+            min: Point3::from_vec(center - world_extents),
+            max: Point3::from_vec(center + world_extents),
+        }
+    }
+}
+
+/// Half line from `origin` along `direction`, which is normalized.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: Point3<f32>,
+    pub direction: Vector3<f32>,
+}
+
+impl Ray {
+    pub fn new(origin: Point3<f32>, direction: Vector3<f32>) -> Self {
+        Self {
+            origin,
+            direction: direction.normalize(),
+        }
+    }
+
+    pub fn at(&self, distance: f32) -> Point3<f32> {
+        self.origin + self.direction * distance
+    }
+
+    /// Same ray in the space of an object moved by `transform`.
+    pub fn to_local(&self, transform: &transform::Transform) -> Self {
+        let inverse = transform.rotation.invert();
+        Self {
+            origin: Point3::from_vec(inverse * (self.origin.to_vec() - transform.position)),
+            direction: inverse * self.direction,
+        }
+    }
+
+    /// Distance to the box, None if it's missed. 0 if the ray starts inside.
+    pub fn intersect_aabb(&self, aabb: &Aabb) -> Option<f32> {
+        let mut near = 0.0f32;
+        let mut far = f32::INFINITY;
+        for axis in 0..3 {
+            let inverse = 1.0 / self.direction[axis];
+            let mut t0 = (aabb.min[axis] - self.origin[axis]) * inverse;
+            let mut t1 = (aabb.max[axis] - self.origin[axis]) * inverse;
+            if inverse < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            // NaN when parallel to a face the ray starts on, max and min ignore it
+            near = near.max(t0);
+            far = far.min(t1);
+            if near > far {
+                return None;
+            }
+        }
+        Some(near)
+    }
+
+    /// Distance to a triangle from either side, None if it's missed.
+    pub fn intersect_triangle(
+        &self,
+        a: Point3<f32>,
+        b: Point3<f32>,
+        c: Point3<f32>,
+    ) -> Option<f32> {
+        // Möller-Trumbore
+        let (ab, ac) = (b - a, c - a);
+        let p = self.direction.cross(ac);
+        let det = ab.dot(p);
+        if det.abs() < f32::EPSILON {
+            return None;
+        }
+
+        let inverse = 1.0 / det;
+        let t = self.origin - a;
+        let u = t.dot(p) * inverse;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let q = t.cross(ab);
+        let v = self.direction.dot(q) * inverse;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        let distance = ac.dot(q) * inverse;
+        (distance >= 0.0).then_some(distance)
+    }
+}
+
+/// Planes of a view volume, points with a non negative distance to all of them are inside.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frustum {
+    /// Normal in xyz and distance in w, normals point inside.
+    pub planes: [Vector4<f32>; 6],
+}
+
+impl Frustum {
+    /// Extracts the planes of a view projection with a depth range of 0 to 1,
+    /// as built by `camera::Projection`.
+    pub fn from_matrix(view_proj: Matrix4<f32>) -> Self {
+        let row = |i| view_proj.row(i);
+        let planes = [
+            row(3) + row(0),
+            row(3) - row(0),
+            row(3) + row(1),
+            row(3) - row(1),
+            row(2),
+            row(3) - row(2),
+        ];
+
+        Self {
+            planes: planes.map(|plane| plane / plane.truncate().magnitude()),
+        }
+    }
+
+    pub fn contains_point(&self, point: Point3<f32>) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.truncate().dot(point.to_vec()) + plane.w >= 0.0)
+    }
+
+    /// False only when the box is fully outside, boxes near a corner may pass.
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        self.planes.iter().all(|plane| {
+            // corner the furthest along the plane's normal
+            let corner = Vector3::new(
+                if plane.x >= 0.0 {
+                    aabb.max.x
+                } else {
+                    aabb.min.x
+                },
+                if plane.y >= 0.0 {
+                    aabb.max.y
+                } else {
+                    aabb.min.y
+                },
+                if plane.z >= 0.0 {
+                    aabb.max.z
+                } else {
+                    aabb.min.z
+                },
+            );
+            plane.truncate().dot(corner) + plane.w >= 0.0
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera;
+
+    fn unit_box(center: (f32, f32, f32)) -> Aabb {
+        let center = Point3::from(center);
+        Aabb::new(
+            center - Vector3::new(1.0, 1.0, 1.0),
+            center + Vector3::new(1.0, 1.0, 1.0),
+        )
+    }
+
+    // 90 degrees wide camera at the origin looking down -z, seeing up to 100 units
+    fn frustum() -> Frustum {
+        let camera = camera::Camera::new((0.0, 0.0, 0.0), cgmath::Deg(-90.0), cgmath::Deg(0.0));
+        let projection = camera::Projection::new(100, 100, cgmath::Deg(90.0), 0.1, 100.0);
+        let mut uniform = camera::CameraUniform::new();
+        uniform.update_view_proj(&camera, &projection);
+        uniform.frustum()
+    }
+
+    #[test]
+    fn from_points_holds_every_point() {
+        let aabb = Aabb::from_points([
+            Point3::new(1.0, -2.0, 0.0),
+            Point3::new(-1.0, 3.0, 0.5),
+            Point3::new(0.0, 0.0, -4.0),
+        ]);
+
+        assert_eq!(aabb.min, Point3::new(-1.0, -2.0, -4.0));
+        assert_eq!(aabb.max, Point3::new(1.0, 3.0, 0.5));
+        assert_eq!(Aabb::from_points([]), Aabb::default());
+    }
+
+    #[test]
+    fn transformed_boxes_hold_the_rotated_corners() {
+        let transform = transform::Transform {
+            position: Vector3::new(10.0, 0.0, 0.0),
+            rotation: cgmath::Quaternion::from_angle_y(cgmath::Deg(45.0)),
+        };
+        let aabb = unit_box((0.0, 0.0, 0.0)).transformed(&transform);
+
+        let half = 2.0f32.sqrt();
+        assert!((aabb.center() - Point3::new(10.0, 0.0, 0.0)).magnitude() < 1e-5);
+        assert!((aabb.extents() - Vector3::new(half, 1.0, half)).magnitude() < 1e-5);
+    }
+
+    #[test]
+    fn frustum_keeps_boxes_in_view() {
+        let frustum = frustum();
+
+        assert!(frustum.contains_point(Point3::new(0.0, 0.0, -10.0)));
+        assert!(frustum.intersects_aabb(&unit_box((0.0, 0.0, -10.0))));
+        assert!(frustum.intersects_aabb(&unit_box((5.0, -5.0, -20.0))));
+    }
+
+    #[test]
+    fn frustum_culls_boxes_out_of_view() {
+        let frustum = frustum();
+
+        assert!(!frustum.contains_point(Point3::new(0.0, 0.0, 10.0)));
+        assert!(!frustum.intersects_aabb(&unit_box((0.0, 0.0, 10.0))));
+        assert!(!frustum.intersects_aabb(&unit_box((30.0, 0.0, -10.0))));
+        assert!(!frustum.intersects_aabb(&unit_box((0.0, -30.0, -10.0))));
+        assert!(!frustum.intersects_aabb(&unit_box((0.0, 0.0, -150.0))));
+    }
+
+    #[test]
+    fn frustum_keeps_boxes_straddling_a_plane() {
+        let frustum = frustum();
+
+        // across the right plane, the near plane and the far plane
+        assert!(frustum.intersects_aabb(&unit_box((10.5, 0.0, -10.0))));
+        assert!(frustum.intersects_aabb(&unit_box((0.0, 0.0, 0.0))));
+        assert!(frustum.intersects_aabb(&unit_box((0.0, 0.0, -100.5))));
+        assert!(!frustum.contains_point(Point3::new(0.0, 0.0, -100.5)));
+    }
+
+    #[test]
+    fn ray_hits_boxes_ahead() {
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -2.0));
+
+        assert_eq!(ray.direction, Vector3::new(0.0, 0.0, -1.0));
+        assert_eq!(ray.intersect_aabb(&unit_box((0.0, 0.0, -10.0))), Some(9.0));
+        assert_eq!(ray.intersect_aabb(&unit_box((0.0, 0.0, 0.5))), Some(0.0));
+    }
+
+    #[test]
+    fn ray_misses_boxes_aside_or_behind() {
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+
+        assert_eq!(ray.intersect_aabb(&unit_box((3.0, 0.0, -10.0))), None);
+        assert_eq!(ray.intersect_aabb(&unit_box((0.0, 0.0, 10.0))), None);
+    }
+
+    #[test]
+    fn ray_hits_triangles_from_either_side() {
+        let (a, b, c) = (
+            Point3::new(-1.0, -1.0, -5.0),
+            Point3::new(1.0, -1.0, -5.0),
+            Point3::new(0.0, 1.0, -5.0),
+        );
+        let forward = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+        let backward = Ray::new(Point3::new(0.0, 0.0, -10.0), Vector3::new(0.0, 0.0, 1.0));
+
+        assert_eq!(forward.intersect_triangle(a, b, c), Some(5.0));
+        assert_eq!(backward.intersect_triangle(a, b, c), Some(5.0));
+    }
+
+    #[test]
+    fn ray_misses_triangles_aside_behind_or_parallel() {
+        let (a, b, c) = (
+            Point3::new(-1.0, -1.0, -5.0),
+            Point3::new(1.0, -1.0, -5.0),
+            Point3::new(0.0, 1.0, -5.0),
+        );
+        let aside = Ray::new(Point3::new(2.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+        let behind = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        let parallel = Ray::new(Point3::new(-5.0, 0.0, -5.0), Vector3::new(1.0, 0.0, 0.0));
+
+        assert_eq!(aside.intersect_triangle(a, b, c), None);
+        assert_eq!(behind.intersect_triangle(a, b, c), None);
+        assert_eq!(parallel.intersect_triangle(a, b, c), None);
+    }
+
+    #[test]
+    fn local_rays_follow_the_inverse_transform() {
+        let transform = transform::Transform {
+            position: Vector3::new(0.0, 0.0, -10.0),
+            rotation: cgmath::Quaternion::from_angle_y(cgmath::Deg(90.0)),
+        };
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+        let local = ray.to_local(&transform);
+
+        assert!((local.origin - Point3::new(-10.0, 0.0, 0.0)).magnitude() < 1e-5);
+        assert!((local.direction - Vector3::new(1.0, 0.0, 0.0)).magnitude() < 1e-5);
+    }
+}
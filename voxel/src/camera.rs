@@ -1,77 +1,219 @@
-use crate::{event::*, fly_camera};
+use crate::{
+    action, actor, bounds, camera_path, delta_time, error, event::*, physics, plugin,
+    profiler::SystemsBuilder, renderer::render, state,
+};
 use cgmath::{prelude::*, Point3};
+use specs::prelude::*;
 use std::{f32::consts, time};
-use wgpu::util::DeviceExt;
 
-#[derive(Default)]
+/// Camera controllers are shared with systems, which may run on any thread.
+pub type BoxedController = Box<dyn CameraController + Send + Sync>;
+
+/// A camera with its controller and where it's drawn.
+///
+/// The `CameraBundle` resource is the main camera, more cameras can be added
+/// as components, e.g. for split-screen or picture-in-picture. Every camera is
+/// drawn each frame, in increasing `priority` order.
 pub struct CameraBundle {
     pub camera: Camera,
     pub projection: Projection,
-    pub controller: fly_camera::FlyCameraController,
+    pub controller: BoxedController,
     pub uniform: CameraUniform,
+    pub viewport: Viewport,
+    pub priority: i32,
+    pub target: RenderTarget,
+    // drives the camera in place of the controller while set
+    path: Option<camera_path::Playback>,
+    // None until drawn, there is no GPU device when headless
+    binding: Option<render::CameraBinding>,
+}
+
+impl Component for CameraBundle {
+    type Storage = HashMapStorage<Self>;
 }
 
-unsafe impl Send for CameraBundle {}
-unsafe impl Sync for CameraBundle {}
+impl Default for CameraBundle {
+    fn default() -> Self {
+        Self {
+            camera: Camera::default(),
+            projection: Projection::default(),
+            controller: Box::new(StaticCameraController),
+            uniform: CameraUniform::default(),
+            viewport: Viewport::default(),
+            priority: 0,
+            target: RenderTarget::default(),
+            path: None,
+            binding: None,
+        }
+    }
+}
 
 impl CameraBundle {
     pub fn from_camera(
         camera: Camera,
         projection: Projection,
-        controller: fly_camera::FlyCameraController,
+        controller: BoxedController,
     ) -> Self {
         let mut camera_uniform = CameraUniform::new();
         camera_uniform.update_view_proj(&camera, &projection);
 
         Self {
             camera,
-            projection: projection,
-            controller: controller,
+            projection,
+            controller,
             uniform: camera_uniform,
+            ..Default::default()
         }
     }
 
+    pub fn with_viewport(mut self, viewport: Viewport) -> Self {
+        self.viewport = viewport;
+        self
+    }
+
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn with_target(mut self, target: RenderTarget) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// Switches to another controller, returning the previous one.
+    pub fn set_controller<C: CameraController + Send + Sync + 'static>(
+        &mut self,
+        controller: C,
+    ) -> BoxedController {
+        std::mem::replace(&mut self.controller, Box::new(controller))
+    }
+
+    /// Moves the camera along `path`, the controller takes over again once it finishes.
+    pub fn play_path(&mut self, path: camera_path::CameraPath) {
+        self.stop_path();
+        self.path = Some(camera_path::Playback::new(path));
+    }
+
+    /// Hands the camera back to the controller, without a Finished event.
+    pub fn stop_path(&mut self) {
+        if let Some(playback) = self.path.take() {
+            playback.restore(&mut self.projection);
+        }
+    }
+
+    pub fn path(&self) -> Option<&camera_path::Playback> {
+        self.path.as_ref()
+    }
+
+    /// World space ray through a cursor position in window pixels, as last drawn.
+    /// None when the cursor is outside of the viewport or the camera draws offscreen.
+    pub fn cursor_ray(&self, (x, y): (f64, f64)) -> Option<bounds::Ray> {
+        if self.target != RenderTarget::Surface {
+            return None;
+        }
+
+        // the projection is sized to the viewport when drawn
+        let (width, height) = (self.projection.width as f64, self.projection.height as f64);
+        let left = self.viewport.x as f64 * width / self.viewport.width as f64;
+        let top = self.viewport.y as f64 * height / self.viewport.height as f64;
+        let (x, y) = ((x - left) / width, (y - top) / height);
+        if !(0.0..1.0).contains(&x) || !(0.0..1.0).contains(&y) {
+            return None;
+        }
+
+        self.uniform
+            .ray((x as f32 * 2.0 - 1.0, 1.0 - y as f32 * 2.0))
+    }
+
     pub fn update(&mut self, dt: time::Duration) {
-        self.controller.update_camera(&mut self.camera, dt);
+        match self.path.as_mut() {
+            Some(playback) => playback.update(&mut self.camera, &mut self.projection, dt),
+            None => self.controller.update_camera(&mut self.camera, dt),
+        }
         self.uniform
             .update_view_proj(&self.camera, &self.projection);
     }
 
-    pub fn build_bind_group(&self, device: &wgpu::Device) -> wgpu::BindGroup {
-        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Camera Buffer"),
-            contents: bytemuck::cast_slice(&[self.uniform]),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
-
-        let camera_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                }],
-                label: Some("camera_bind_group_layout"),
-            });
-
-        let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &camera_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: camera_buffer.as_entire_binding(),
-            }],
-            label: Some("camera_bind_group"),
-        });
-
-        camera_bind_group
+    /// Writes the view projection to the camera's uniform buffer, which is
+    /// created with its bind group the first time the camera is drawn.
+    pub fn write_uniform(&mut self, render: &render::Render) {
+        let binding = self
+            .binding
+            .get_or_insert_with(|| render.create_camera_binding());
+        render.write_camera(binding, &self.uniform);
+    }
+
+    /// None until the camera is first drawn.
+    pub fn bind_group(&self) -> Option<&wgpu::BindGroup> {
+        self.binding.as_ref().map(|binding| binding.bind_group())
     }
 }
 
+/// Area of the render target a camera draws to, in fractions of the
+/// target's size from its top left corner.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Self::new(0.0, 0.0, 1.0, 1.0)
+    }
+}
+
+impl Viewport {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Column `index` of `count` side by side views.
+    pub fn columns(index: usize, count: usize) -> Self {
+        let width = 1.0 / count.max(1) as f32;
+        Self::new(index as f32 * width, 0.0, width, 1.0)
+    }
+
+    /// Row `index` of `count` stacked views, from the top.
+    pub fn rows(index: usize, count: usize) -> Self {
+        let height = 1.0 / count.max(1) as f32;
+        Self::new(0.0, index as f32 * height, 1.0, height)
+    }
+
+    /// Position and size in pixels on a target of `size` pixels, clamped to the target.
+    pub fn rect(&self, (width, height): (u32, u32)) -> (f32, f32, f32, f32) {
+        let (width, height) = (width as f32, height as f32);
+        let x = (self.x * width).clamp(0.0, width);
+        let y = (self.y * height).clamp(0.0, height);
+        let w = (self.width * width).clamp(0.0, width - x);
+        let h = (self.height * height).clamp(0.0, height - y);
+        (x, y, w, h)
+    }
+
+    pub fn pixel_size(&self, size: (u32, u32)) -> (u32, u32) {
+        let (_, _, width, height) = self.rect(size);
+        (width as u32, height as u32)
+    }
+}
+
+/// Where a camera is drawn.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum RenderTarget {
+    /// The window.
+    #[default]
+    Surface,
+    /// An offscreen texture created with `Render::create_render_target`.
+    Texture(String),
+}
+
 pub struct Camera {
     pub position: Point3<f32>,
     pub yaw: cgmath::Rad<f32>,
@@ -101,22 +243,71 @@ impl Camera {
         }
     }
 
-    pub fn matrix(&self) -> cgmath::Matrix4<f32> {
+    pub fn forward(&self) -> cgmath::Vector3<f32> {
         let (sin_pitch, cos_pitch) = self.pitch.0.sin_cos();
         let (sin_yaw, cos_yaw) = self.yaw.0.sin_cos();
 
-        cgmath::Matrix4::look_to_rh(
-            self.position,
-            cgmath::Vector3::new(cos_pitch * cos_yaw, sin_pitch, cos_pitch * sin_yaw).normalize(),
-            cgmath::Vector3::unit_y(),
-        )
+        cgmath::Vector3::new(cos_pitch * cos_yaw, sin_pitch, cos_pitch * sin_yaw).normalize()
+    }
+
+    /// Turns the camera towards `target`, keeping its position.
+    pub fn look_at(&mut self, target: Point3<f32>) {
+        let direction = target - self.position;
+        if direction.magnitude2() <= f32::EPSILON {
+            return;
+        }
+
+        let direction = direction.normalize();
+        self.yaw = cgmath::Rad(direction.z.atan2(direction.x));
+        self.pitch = cgmath::Rad(direction.y.asin().clamp(-SAFE_FRAC_PI_2, SAFE_FRAC_PI_2));
+    }
+
+    /// Looks down at `target` from `distance` away along the isometric diagonal,
+    /// meant for an orthographic projection.
+    pub fn isometric<P: Into<Point3<f32>>>(target: P, distance: f32) -> Self {
+        let mut camera = Self {
+            position: Point3::origin(),
+            yaw: cgmath::Deg(-135.0).into(),
+            pitch: cgmath::Rad(-(1.0f32 / consts::SQRT_2).atan()),
+        };
+        camera.position = target.into() - camera.forward() * distance;
+        camera
+    }
+
+    pub fn matrix(&self) -> cgmath::Matrix4<f32> {
+        cgmath::Matrix4::look_to_rh(self.position, self.forward(), cgmath::Vector3::unit_y())
+    }
+}
+
+/// Size of an orthographic view, the width follows from the aspect ratio.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OrthographicSize {
+    /// World units covered by the view's height.
+    Height(f32),
+    /// Screen pixels per world unit, the view grows with the surface.
+    PixelsPerUnit(f32),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ProjectionMode {
+    Perspective { fovy: cgmath::Rad<f32> },
+    Orthographic(OrthographicSize),
+}
+
+impl Default for ProjectionMode {
+    fn default() -> Self {
+        ProjectionMode::Perspective {
+            fovy: cgmath::Deg(45.0).into(),
+        }
     }
 }
 
 #[derive(Clone)]
 pub struct Projection {
-    pub aspect: f32,
-    pub fovy: cgmath::Rad<f32>,
+    pub mode: ProjectionMode,
+    /// Viewport size in pixels, set by `GameTicker` before every draw.
+    pub width: u32,
+    pub height: u32,
     pub znear: f32,
     pub zfar: f32,
 }
@@ -124,10 +315,11 @@ pub struct Projection {
 impl Default for Projection {
     fn default() -> Self {
         Self {
-            aspect: 0.0,
-            fovy: cgmath::Rad(0.0),
-            zfar: 0.0,
-            znear: 0.0,
+            mode: ProjectionMode::default(),
+            width: 1,
+            height: 1,
+            znear: 0.1,
+            zfar: 100.0,
         }
     }
 }
@@ -140,14 +332,61 @@ impl Projection {
         znear: f32,
         zfar: f32,
     ) -> Self {
+        Self::with_mode(
+            width,
+            height,
+            ProjectionMode::Perspective { fovy: fovy.into() },
+            znear,
+            zfar,
+        )
+    }
+
+    pub fn orthographic(
+        width: u32,
+        height: u32,
+        size: OrthographicSize,
+        znear: f32,
+        zfar: f32,
+    ) -> Self {
+        Self::with_mode(
+            width,
+            height,
+            ProjectionMode::Orthographic(size),
+            znear,
+            zfar,
+        )
+    }
+
+    pub fn with_mode(width: u32, height: u32, mode: ProjectionMode, znear: f32, zfar: f32) -> Self {
         Self {
-            aspect: width as f32 / height as f32,
-            fovy: fovy.into(),
+            mode,
+            width: width.max(1),
+            height: height.max(1),
             znear,
             zfar,
         }
     }
 
+    pub fn aspect(&self) -> f32 {
+        self.width as f32 / self.height as f32
+    }
+
+    /// Width and height of the orthographic view in world units, None for perspective.
+    pub fn view_size(&self) -> Option<(f32, f32)> {
+        match self.mode {
+            ProjectionMode::Perspective { .. } => None,
+            ProjectionMode::Orthographic(size) => Some(self.orthographic_size(size)),
+        }
+    }
+
+    fn orthographic_size(&self, size: OrthographicSize) -> (f32, f32) {
+        let height = match size {
+            OrthographicSize::Height(height) => height,
+            OrthographicSize::PixelsPerUnit(pixels) => self.height as f32 / pixels,
+        };
+        (height * self.aspect(), height)
+    }
+
     #[rustfmt::skip]
     pub const OPENGL_TO_WGPU_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::new(
         1.0, 0.0, 0.0, 0.0,
@@ -157,13 +396,29 @@ impl Projection {
     );
 
     pub fn build_view_projection_matrix(&self) -> cgmath::Matrix4<f32> {
-        Self::OPENGL_TO_WGPU_MATRIX
-            * cgmath::perspective(self.fovy, self.aspect, self.znear, self.zfar)
+        let projection = match self.mode {
+            ProjectionMode::Perspective { fovy } => {
+                cgmath::perspective(fovy, self.aspect(), self.znear, self.zfar)
+            }
+            ProjectionMode::Orthographic(size) => {
+                let (width, height) = self.orthographic_size(size);
+                cgmath::ortho(
+                    -width / 2.0,
+                    width / 2.0,
+                    -height / 2.0,
+                    height / 2.0,
+                    self.znear,
+                    self.zfar,
+                )
+            }
+        };
+        Self::OPENGL_TO_WGPU_MATRIX * projection
     }
 
     pub fn resize(&mut self, (width, height): (u32, u32)) {
         if width > 0 && height > 0 {
-            self.aspect = width as f32 / height as f32;
+            self.width = width;
+            self.height = height;
         }
     }
 }
@@ -181,17 +436,202 @@ impl CameraUniform {
         }
     }
 
+    pub fn view_proj(&self) -> cgmath::Matrix4<f32> {
+        self.view_proj.into()
+    }
+
+    pub fn frustum(&self) -> bounds::Frustum {
+        bounds::Frustum::from_matrix(self.view_proj())
+    }
+
+    /// World space ray through a point in normalized device coordinates,
+    /// from the near to the far plane.
+    pub fn ray(&self, (x, y): (f32, f32)) -> Option<bounds::Ray> {
+        let inverse = self.view_proj().invert()?;
+        let unproject = |z: f32| {
+            let point = inverse * cgmath::Vector4::new(x, y, z, 1.0);
+            Point3::from_homogeneous(point)
+        };
+        let (near, far) = (unproject(0.0), unproject(1.0));
+
+        Some(bounds::Ray::new(near, far - near))
+    }
+
     pub fn update_view_proj(&mut self, camera: &Camera, projection: &Projection) {
         // self.view_position = camera.position.to_homogeneous().into();
         self.view_proj = (projection.build_view_projection_matrix() * camera.matrix()).into();
     }
 }
 
-pub trait CameraController {
-    const SAFE_FRAC_PI_2: f32 = consts::FRAC_PI_2 - 0.0001;
+/// Pitch limit keeping cameras from flipping over when looking straight up or down.
+pub const SAFE_FRAC_PI_2: f32 = consts::FRAC_PI_2 - 0.0001;
 
-    fn process_events(&mut self, event: &WinEvent) -> bool;
-    fn process_mouse(&mut self, mouse_dx: f64, mouse_dy: f64);
-    fn process_scroll(&mut self, dt: f64);
+/// Moves a `Camera`, `CameraBundle` holds one and `CameraSys` feeds it input.
+pub trait CameraController {
+    /// Returns true if the event was used.
+    fn process_events(&mut self, _event: &WinEvent) -> bool {
+        false
+    }
+    /// Called once per step after the events, for controllers polling held actions.
+    fn process_actions(&mut self, _actions: &action::Actions) {}
+    /// Called once per step, for controllers following entities.
+    fn process_world(&mut self, _world: &CameraWorld) {}
+    fn process_mouse(&mut self, _mouse_dx: f64, _mouse_dy: f64) {}
+    /// `dt` is in pixels.
+    fn process_scroll(&mut self, _dt: f64) {}
+    /// Called before every frame is drawn, `dt` is the time since the previous one.
+    /// Mouse and scroll deltas gathered since then are distances, not speeds,
+    /// so they are applied without scaling by `dt`.
     fn update_camera(&mut self, camera: &mut Camera, dt: time::Duration);
 }
+
+/// World state `CameraSys` hands to the controller on every step.
+pub struct CameraWorld<'s, 'a> {
+    /// Simulation time of the step.
+    pub dt: time::Duration,
+    pub actors: &'s ReadStorage<'a, actor::Actor>,
+    pub rigid_bodies: &'s ReadStorage<'a, physics::RigidBodyComponent>,
+    /// None unless the physics plugin is added.
+    pub physics: Option<&'s physics::Physics>,
+}
+
+/// Leaves the camera where it is, for cutscenes or cameras moved by systems.
+pub struct StaticCameraController;
+
+impl CameraController for StaticCameraController {
+    fn update_camera(&mut self, _camera: &mut Camera, _dt: time::Duration) {}
+}
+
+/// Feeds window events, actions and the world to the controllers of the
+/// main camera and of every camera component, and reports camera paths.
+pub struct CameraSys;
+
+impl<'a> System<'a> for CameraSys {
+    type SystemData = (
+        Entities<'a>,
+        Option<Write<'a, CameraBundle>>,
+        WriteStorage<'a, CameraBundle>,
+        Write<'a, Events<camera_path::CameraPathEvent>>,
+        Read<'a, WinEvents>,
+        Read<'a, action::Actions>,
+        Read<'a, delta_time::DeltaTime>,
+        ReadStorage<'a, actor::Actor>,
+        ReadStorage<'a, physics::RigidBodyComponent>,
+        Option<Read<'a, physics::Physics>>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            mut main_camera,
+            mut cameras,
+            mut path_events,
+            events,
+            actions,
+            dt,
+            actors,
+            rigid_bodies,
+            physics,
+        ): Self::SystemData,
+    ) {
+        log::trace!("running cameraSys system");
+
+        let world = CameraWorld {
+            dt: dt.dt,
+            actors: &actors,
+            rigid_bodies: &rigid_bodies,
+            physics: physics.as_deref(),
+        };
+        let cameras = main_camera
+            .as_deref_mut()
+            .map(|camera| (None, camera))
+            .into_iter()
+            .chain(
+                (&entities, &mut cameras)
+                    .join()
+                    .map(|(entity, camera)| (Some(entity), camera)),
+            );
+        for (entity, camera) in cameras {
+            if let Some(playback) = camera.path.as_mut() {
+                let event = playback.poll_event(entity);
+                if let Some(camera_path::CameraPathEvent::Finished { .. }) = event {
+                    camera.stop_path();
+                }
+                path_events.send_batch(event);
+                // input would pile up until the controller takes over
+                continue;
+            }
+
+            for ev in &events.events {
+                camera.controller.process_events(ev);
+            }
+            camera.controller.process_actions(&actions);
+            camera.controller.process_world(&world);
+        }
+    }
+}
+
+/// Inserts the main `CameraBundle` and adds `CameraSys`.
+pub struct CameraPlugin {
+    pub camera: Camera,
+    pub controller: Option<BoxedController>,
+    pub mode: ProjectionMode,
+    pub znear: f32,
+    pub zfar: f32,
+}
+
+impl CameraPlugin {
+    pub fn new<C: CameraController + Send + Sync + 'static>(camera: Camera, controller: C) -> Self {
+        Self {
+            camera,
+            controller: Some(Box::new(controller)),
+            mode: ProjectionMode::default(),
+            znear: 0.1,
+            zfar: 100.0,
+        }
+    }
+
+    /// Static orthographic camera looking down at `target` along the isometric
+    /// diagonal, `view_height` world units fit the window's height.
+    pub fn isometric<P: Into<Point3<f32>>>(target: P, view_height: f32) -> Self {
+        let distance = 100.0;
+
+        Self {
+            camera: Camera::isometric(target, distance),
+            controller: Some(Box::new(StaticCameraController)),
+            mode: ProjectionMode::Orthographic(OrthographicSize::Height(view_height)),
+            znear: 0.1,
+            zfar: distance * 2.0,
+        }
+    }
+
+    pub fn with_projection(mut self, mode: ProjectionMode) -> Self {
+        self.mode = mode;
+        self
+    }
+}
+
+impl plugin::Plugin for CameraPlugin {
+    fn setup(&mut self, global_state: &mut state::State) -> error::Result<()> {
+        global_state.add_event::<camera_path::CameraPathEvent>();
+        let (width, height) = global_state.size();
+        let projection = Projection::with_mode(width, height, self.mode, self.znear, self.zfar);
+        global_state.world.insert(CameraBundle::from_camera(
+            std::mem::take(&mut self.camera),
+            projection,
+            self.controller
+                .take()
+                .unwrap_or_else(|| Box::new(StaticCameraController)),
+        ));
+
+        Ok(())
+    }
+
+    fn setup_systems<'a, 'b>(
+        &mut self,
+        dispatcher_builder: SystemsBuilder<'a, 'b>,
+    ) -> SystemsBuilder<'a, 'b> {
+        dispatcher_builder.with(CameraSys, "camera_sys", &[])
+    }
+}
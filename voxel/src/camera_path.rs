@@ -0,0 +1,442 @@
+use std::path::Path;
+use std::time;
+
+use cgmath::{prelude::*, Point3, Rad, Vector3};
+use serde::{Deserialize, Serialize};
+
+use crate::{camera, error, ron_file};
+
+/// Where the camera looks at a keyframe.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Look {
+    Angles { yaw: Rad<f32>, pitch: Rad<f32> },
+    At(Point3<f32>),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Keyframe {
+    /// Seconds from the start of the path.
+    pub time: f32,
+    pub position: Point3<f32>,
+    pub look: Look,
+    /// Vertical field of view in radians. If None, it blends between the
+    /// closest keyframes setting one, or holds the only one on its side.
+    /// The projection's own is kept if no keyframe sets it.
+    #[serde(default)]
+    pub fovy: Option<Rad<f32>>,
+}
+
+impl Keyframe {
+    pub fn new<P: Into<Point3<f32>>>(time: f32, position: P, look: Look) -> Self {
+        Self {
+            time,
+            position: position.into(),
+            look,
+            fovy: None,
+        }
+    }
+
+    pub fn with_fovy<F: Into<Rad<f32>>>(mut self, fovy: F) -> Self {
+        self.fovy = Some(fovy.into());
+        self
+    }
+
+    // angles look one unit ahead, so both kinds interpolate as points
+    fn target(&self) -> Point3<f32> {
+        match self.look {
+            Look::At(target) => target,
+            Look::Angles { yaw, pitch } => {
+                self.position + camera::Camera::new(self.position, yaw, pitch).forward()
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Interpolation {
+    Linear,
+    /// Smooth curve through every keyframe.
+    #[default]
+    CatmullRom,
+}
+
+/// Remaps the playback time, e.g. to slow down at the start and the end.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Easing {
+    #[default]
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+
+impl Easing {
+    /// Maps `t` from 0 to 1 onto 0 to 1.
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+/// Camera state on a path at some time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PathSample {
+    pub position: Point3<f32>,
+    pub target: Point3<f32>,
+    pub fovy: Option<Rad<f32>>,
+}
+
+/// Keyframed camera path for flythroughs and cutscenes, as stored in a RON file:
+///
+/// ```ron
+/// (
+///     name: "intro",
+///     keyframes: [
+///         (
+///             time: 0.0,
+///             position: (x: 0.0, y: 20.0, z: 30.0),
+///             look: At((x: 0.0, y: 0.0, z: 0.0)),
+///         ),
+///         (
+///             time: 4.0,
+///             position: (x: 20.0, y: 5.0, z: 0.0),
+///             look: Angles(yaw: (3.14), pitch: (-0.2)),
+///             fovy: Some((0.6)),
+///         ),
+///     ],
+///     easing: EaseInOut,
+/// )
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CameraPath {
+    pub name: String,
+    /// Sorted by time.
+    pub keyframes: Vec<Keyframe>,
+    #[serde(default)]
+    pub interpolation: Interpolation,
+    #[serde(default)]
+    pub easing: Easing,
+    /// Stretches the keyframe times to last this long, the last keyframe's time if None.
+    #[serde(default)]
+    pub duration: Option<time::Duration>,
+    /// Starts over once finished instead of handing the camera back.
+    #[serde(default)]
+    pub looping: bool,
+}
+
+impl CameraPath {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            keyframes: Vec::new(),
+            interpolation: Interpolation::default(),
+            easing: Easing::default(),
+            duration: None,
+            looping: false,
+        }
+    }
+
+    pub fn with_keyframe(mut self, keyframe: Keyframe) -> Self {
+        self.add_keyframe(keyframe);
+        self
+    }
+
+    pub fn with_interpolation(mut self, interpolation: Interpolation) -> Self {
+        self.interpolation = interpolation;
+        self
+    }
+
+    pub fn with_easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+
+    pub fn with_duration(mut self, duration: time::Duration) -> Self {
+        self.duration = Some(duration);
+        self
+    }
+
+    pub fn with_looping(mut self, looping: bool) -> Self {
+        self.looping = looping;
+        self
+    }
+
+    /// Inserts a keyframe, keeping them sorted by time.
+    pub fn add_keyframe(&mut self, keyframe: Keyframe) {
+        let index = self
+            .keyframes
+            .partition_point(|key| key.time <= keyframe.time);
+        self.keyframes.insert(index, keyframe);
+    }
+
+    pub fn from_ron(src: &str) -> Result<Self, ron::error::SpannedError> {
+        let mut path: Self = ron::from_str(src)?;
+        path.sort_keyframes();
+        Ok(path)
+    }
+
+    pub fn to_ron(&self) -> Result<String, ron::Error> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> error::Result<Self> {
+        let mut path: Self = ron_file::load(path)?;
+        path.sort_keyframes();
+        Ok(path)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> error::Result<()> {
+        ron_file::save(path, self)
+    }
+
+    // files may list keyframes in any order
+    fn sort_keyframes(&mut self) {
+        self.keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+    }
+
+    fn keys_duration(&self) -> f32 {
+        self.keyframes.last().map_or(0.0, |key| key.time.max(0.0))
+    }
+
+    pub fn duration(&self) -> time::Duration {
+        self.duration
+            .unwrap_or_else(|| time::Duration::from_secs_f32(self.keys_duration()))
+    }
+
+    /// Camera state `elapsed` after the start, None without keyframes.
+    pub fn sample(&self, elapsed: time::Duration) -> Option<PathSample> {
+        let duration = self.duration().as_secs_f32();
+        let t = if duration > 0.0 {
+            self.easing.apply(elapsed.as_secs_f32() / duration) * self.keys_duration()
+        } else {
+            0.0
+        };
+
+        let last = self.keyframes.len().checked_sub(1)?;
+        let i = self.keyframes[..last]
+            .partition_point(|key| key.time <= t)
+            .max(1)
+            - 1;
+        let next = (i + 1).min(last);
+        let (k1, k2) = (&self.keyframes[i], &self.keyframes[next]);
+        let span = k2.time - k1.time;
+        let u = if span > 0.0 {
+            ((t - k1.time) / span).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+
+        let k0 = &self.keyframes[i.saturating_sub(1)];
+        let k3 = &self.keyframes[(i + 2).min(last)];
+        let point = |f: fn(&Keyframe) -> Point3<f32>| {
+            let (p0, p1, p2, p3) = (f(k0), f(k1), f(k2), f(k3));
+            match self.interpolation {
+                Interpolation::Linear => p1 + (p2 - p1) * u,
+                Interpolation::CatmullRom => Point3::from_vec(catmull_rom(
+                    p0.to_vec(),
+                    p1.to_vec(),
+                    p2.to_vec(),
+                    p3.to_vec(),
+                    u,
+                )),
+            }
+        };
+        let fovy_at = |key: &Keyframe| key.fovy.map(|fovy| (key.time, fovy));
+        let before = self.keyframes[..=i].iter().rev().find_map(fovy_at);
+        let after = self.keyframes[next..].iter().find_map(fovy_at);
+        let fovy = match (before, after) {
+            (Some((t0, a)), Some((t1, b))) if t1 > t0 => {
+                Some(a + (b - a) * ((t - t0) / (t1 - t0)).clamp(0.0, 1.0))
+            }
+            (before, after) => before.or(after).map(|(_, fovy)| fovy),
+        };
+
+        Some(PathSample {
+            position: point(|key| key.position),
+            target: point(Keyframe::target),
+            fovy,
+        })
+    }
+}
+
+fn catmull_rom(
+    p0: Vector3<f32>,
+    p1: Vector3<f32>,
+    p2: Vector3<f32>,
+    p3: Vector3<f32>,
+    u: f32,
+) -> Vector3<f32> {
+    let (u2, u3) = (u * u, u * u * u);
+    (p1 * 2.0
+        + (p2 - p0) * u
+        + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * u2
+        + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * u3)
+        * 0.5
+}
+
+/// A path driving a camera, see `CameraBundle::play_path`.
+pub struct Playback {
+    pub path: CameraPath,
+    pub elapsed: time::Duration,
+    started: bool,
+    finished: bool,
+    // field of view to restore once the path is done with the camera
+    fovy: Option<Rad<f32>>,
+}
+
+impl Playback {
+    pub fn new(path: CameraPath) -> Self {
+        Self {
+            path,
+            elapsed: time::Duration::ZERO,
+            started: false,
+            finished: false,
+            fovy: None,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Started once the playback began, then Finished once it's done.
+    pub fn poll_event(&mut self, camera: Option<specs::Entity>) -> Option<CameraPathEvent> {
+        let path = self.path.name.clone();
+        if !self.started {
+            self.started = true;
+            Some(CameraPathEvent::Started { camera, path })
+        } else if self.finished {
+            Some(CameraPathEvent::Finished { camera, path })
+        } else {
+            None
+        }
+    }
+
+    /// Moves the camera `dt` further along the path.
+    pub fn update(
+        &mut self,
+        camera: &mut camera::Camera,
+        projection: &mut camera::Projection,
+        dt: time::Duration,
+    ) {
+        let duration = self.path.duration();
+        self.elapsed += dt;
+        if self.elapsed >= duration {
+            if self.path.looping && !duration.is_zero() {
+                self.elapsed = time::Duration::from_nanos(
+                    (self.elapsed.as_nanos() % duration.as_nanos()) as u64,
+                );
+            } else {
+                self.elapsed = duration;
+                self.finished = true;
+            }
+        }
+
+        let sample = match self.path.sample(self.elapsed) {
+            Some(sample) => sample,
+            None => {
+                self.finished = true;
+                return;
+            }
+        };
+        camera.position = sample.position;
+        camera.look_at(sample.target);
+        if let (Some(fovy), camera::ProjectionMode::Perspective { fovy: current }) =
+            (sample.fovy, &mut projection.mode)
+        {
+            self.fovy.get_or_insert(*current);
+            *current = fovy;
+        }
+    }
+
+    /// Gives the projection its field of view back.
+    pub fn restore(&self, projection: &mut camera::Projection) {
+        if let (Some(fovy), camera::ProjectionMode::Perspective { fovy: current }) =
+            (self.fovy, &mut projection.mode)
+        {
+            *current = fovy;
+        }
+    }
+}
+
+/// Sent by `CameraSys` when a camera starts or finishes a path,
+/// `camera` is None for the main camera. Stopped and looping paths never finish.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CameraPathEvent {
+    Started {
+        camera: Option<specs::Entity>,
+        path: String,
+    },
+    Finished {
+        camera: Option<specs::Entity>,
+        path: String,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(time: f32, fovy: Option<f32>) -> Keyframe {
+        let key = Keyframe::new(time, (time, 0.0, 0.0), Look::At((0.0, 0.0, -10.0).into()));
+        match fovy {
+            Some(fovy) => key.with_fovy(Rad(fovy)),
+            None => key,
+        }
+    }
+
+    fn sample(path: &CameraPath, time: f32) -> PathSample {
+        path.sample(time::Duration::from_secs_f32(time)).unwrap()
+    }
+
+    fn assert_near(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-5, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn fovy_blends_across_keyframes_without_one() {
+        let path = CameraPath::new("fovy")
+            .with_keyframe(key(0.0, Some(1.0)))
+            .with_keyframe(key(1.0, None))
+            .with_keyframe(key(2.0, None))
+            .with_keyframe(key(4.0, Some(0.2)));
+
+        assert_near(sample(&path, 0.0).fovy.unwrap().0, 1.0);
+        assert_near(sample(&path, 2.0).fovy.unwrap().0, 0.6);
+        assert_near(sample(&path, 4.0).fovy.unwrap().0, 0.2);
+    }
+
+    #[test]
+    fn fovy_holds_the_only_keyframe_setting_one() {
+        let path = CameraPath::new("fovy")
+            .with_keyframe(key(0.0, None))
+            .with_keyframe(key(1.0, None))
+            .with_keyframe(key(2.0, Some(0.5)))
+            .with_keyframe(key(3.0, None));
+
+        assert_eq!(sample(&path, 0.5).fovy, Some(Rad(0.5)));
+        assert_eq!(sample(&path, 3.0).fovy, Some(Rad(0.5)));
+
+        let path = CameraPath::new("none").with_keyframe(key(0.0, None));
+        assert_eq!(sample(&path, 0.0).fovy, None);
+    }
+
+    #[test]
+    fn positions_pass_through_keyframes() {
+        let path = CameraPath::new("linear")
+            .with_interpolation(Interpolation::Linear)
+            .with_keyframe(key(2.0, None))
+            .with_keyframe(key(0.0, None));
+
+        assert_near(sample(&path, 1.0).position.x, 1.0);
+        assert_near(sample(&path, 5.0).position.x, 2.0);
+        assert!(CameraPath::new("empty")
+            .sample(time::Duration::ZERO)
+            .is_none());
+    }
+}
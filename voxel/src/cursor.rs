@@ -0,0 +1,94 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CursorGrab {
+    None,
+    /// The cursor can't leave the window.
+    Confined,
+    /// The cursor stays at the window's center, only raw mouse motion is reported.
+    Locked,
+}
+
+/// Cursor settings applied to the window after every tick.
+///
+/// The engine releases the cursor when the window loses focus or Esc is
+/// pressed, and restores these settings once the window is focused or
+/// clicked again. Esc only closes the window while the cursor is free.
+pub struct Cursor {
+    grab: CursorGrab,
+    visible: bool,
+    // released by the engine, the settings are kept for restore
+    suspended: bool,
+}
+
+impl Default for Cursor {
+    fn default() -> Self {
+        Self {
+            grab: CursorGrab::None,
+            visible: true,
+            suspended: false,
+        }
+    }
+}
+
+impl Cursor {
+    pub fn set_grab(&mut self, grab: CursorGrab) {
+        self.grab = grab;
+    }
+
+    pub fn grab(&self) -> CursorGrab {
+        self.grab
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Relative mode locks and hides the cursor, for mouse look.
+    pub fn set_relative(&mut self, relative: bool) {
+        if relative {
+            self.grab = CursorGrab::Locked;
+            self.visible = false;
+        } else {
+            self.grab = CursorGrab::None;
+            self.visible = true;
+        }
+    }
+
+    pub fn is_relative(&self) -> bool {
+        self.grab == CursorGrab::Locked && !self.visible
+    }
+
+    pub fn toggle_relative(&mut self) {
+        self.set_relative(!self.is_relative());
+    }
+
+    /// True while the settings differ from a free, visible cursor.
+    pub fn is_captured(&self) -> bool {
+        self.grab != CursorGrab::None || !self.visible
+    }
+
+    /// Frees the cursor until `restore` is called, keeping the settings.
+    pub fn suspend(&mut self) {
+        self.suspended = true;
+    }
+
+    pub fn restore(&mut self) {
+        self.suspended = false;
+    }
+
+    pub fn is_suspended(&self) -> bool {
+        self.suspended
+    }
+
+    /// Grab and visibility the window should currently have.
+    pub fn effective(&self) -> (CursorGrab, bool) {
+        if self.suspended {
+            (CursorGrab::None, true)
+        } else {
+            (self.grab, self.visible)
+        }
+    }
+}
@@ -1,29 +1,19 @@
 use std::time;
 
+/// Game time advanced by the game ticker, one simulation step at a time.
+#[derive(Default)]
 pub struct DeltaTime {
+    /// Game time advanced so far, it stands still while the `GameClock` is paused.
     pub last: time::Duration,
     pub dt: time::Duration,
 }
 
-impl Default for DeltaTime {
-    fn default() -> Self {
-        Self{
-            last: now(),
-            dt: time::Duration::from_secs(0),
-        }
-    }
-}
-
 impl DeltaTime {
-    pub fn tick(&mut self) -> time::Duration {
-        let now = now();
-        self.dt = now - self.last;
-        self.last = now;
+    /// Advances by a simulation step, see `GameClock`.
+    pub fn advance(&mut self, step: time::Duration) -> time::Duration {
+        self.dt = step;
+        self.last += step;
 
         self.dt
     }
 }
-
-pub fn now() -> time::Duration {
-    time::SystemTime::now().duration_since(time::UNIX_EPOCH).unwrap()
-}
\ No newline at end of file
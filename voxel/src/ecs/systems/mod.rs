@@ -1 +1 @@
-pub mod delta_time;
\ No newline at end of file
+pub mod timer;
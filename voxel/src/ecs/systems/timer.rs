@@ -0,0 +1,34 @@
+use crate::{delta_time, event, timer};
+use specs::prelude::*;
+
+#[derive(Default)]
+pub struct TimerSys {
+    finished: Vec<timer::TimerId>,
+}
+
+impl<'a> System<'a> for TimerSys {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, delta_time::DeltaTime>,
+        Read<'a, LazyUpdate>,
+        Write<'a, timer::Timers>,
+        WriteStorage<'a, timer::Timer>,
+        Write<'a, event::Events<timer::TimerFinished>>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, dt, lazy, mut timers, mut entity_timers, mut events): Self::SystemData,
+    ) {
+        log::trace!("running TimerSys system");
+
+        timers.tick(dt.dt, &lazy, &mut self.finished);
+        events.send_batch(self.finished.drain(..).map(timer::TimerFinished::Scheduled));
+
+        for (entity, entity_timer) in (&entities, &mut entity_timers).join() {
+            for _ in 0..entity_timer.tick(dt.dt) {
+                events.send(timer::TimerFinished::Entity(entity));
+            }
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use std::fmt;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    /// The window could not be created.
+    Window(winit::error::OsError),
+    /// No GPU adapter is compatible with the window surface.
+    AdapterNotFound,
+    /// The adapter refused to hand over a device.
+    RequestDevice(wgpu::RequestDeviceError),
+    /// The surface reports no texture format for the adapter.
+    UnsupportedSurface,
+    /// The GPU ran out of memory while acquiring a surface texture.
+    SurfaceOutOfMemory,
+    /// The systems thread pool could not be spawned.
+    ThreadPool(specs::rayon::ThreadPoolBuildError),
+    /// A model, shader or other resource failed to load.
+    Asset { path: String, source: anyhow::Error },
+    /// A setting is out of its valid range.
+    InvalidConfig(&'static str),
+}
+
+impl Error {
+    pub fn asset(path: &str, source: anyhow::Error) -> Self {
+        Error::Asset {
+            path: path.to_string(),
+            source,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Window(err) => write!(f, "failed to create window: {}", err),
+            Error::AdapterNotFound => write!(f, "no compatible GPU adapter found"),
+            Error::RequestDevice(err) => write!(f, "failed to request GPU device: {}", err),
+            Error::UnsupportedSurface => write!(f, "surface is not supported by the adapter"),
+            Error::SurfaceOutOfMemory => write!(f, "GPU out of memory"),
+            Error::ThreadPool(err) => write!(f, "failed to build thread pool: {}", err),
+            Error::Asset { path, source } => write!(f, "failed to load {}: {}", path, source),
+            Error::InvalidConfig(reason) => write!(f, "invalid configuration: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Window(err) => Some(err),
+            Error::RequestDevice(err) => Some(err),
+            Error::ThreadPool(err) => Some(err),
+            Error::Asset { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<winit::error::OsError> for Error {
+    fn from(err: winit::error::OsError) -> Self {
+        Error::Window(err)
+    }
+}
+
+impl From<wgpu::RequestDeviceError> for Error {
+    fn from(err: wgpu::RequestDeviceError) -> Self {
+        Error::RequestDevice(err)
+    }
+}
+
+impl From<specs::rayon::ThreadPoolBuildError> for Error {
+    fn from(err: specs::rayon::ThreadPoolBuildError) -> Self {
+        Error::ThreadPool(err)
+    }
+}
@@ -1,25 +1,71 @@
-#[derive(Clone, Debug)]
+use serde::{Deserialize, Serialize};
+
+pub use winit::event::{ModifiersState as Modifiers, MouseButton, VirtualKeyCode as Key};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum WinEvent {
-    LShift,
-    Space,
-    W,
-    A,
-    S,
-    D,
-    Up,
-    Down,
-    Left,
-    Right,
+    /// `key` is None for keys winit can't map, `scancode` is always set.
+    Key {
+        key: Option<Key>,
+        scancode: u32,
+        state: ButtonState,
+        modifiers: Modifiers,
+    },
+    MouseButton {
+        button: MouseButton,
+        state: ButtonState,
+        modifiers: Modifiers,
+    },
+    /// Raw mouse motion, not affected by the cursor position or acceleration.
     MouseMotion(f64, f64),
+    /// Cursor position in physical pixels from the window's top left corner.
+    CursorMoved(f64, f64),
+    CursorEntered,
+    CursorLeft,
     Scroll(MouseScroll),
-    Esc,
+    ModifiersChanged(Modifiers),
+    Focused(bool),
     Close,
     Redraw,
     Resize(u32, u32),
     Nothing,
 }
 
-#[derive(Clone, Debug)]
+impl WinEvent {
+    /// True if `self` is `key` being pressed, including repeats.
+    pub fn is_key_pressed(&self, key: Key) -> bool {
+        matches!(self, WinEvent::Key { key: Some(k), state: ButtonState::Pressed, .. } if *k == key)
+    }
+
+    pub fn is_key_released(&self, key: Key) -> bool {
+        matches!(self, WinEvent::Key { key: Some(k), state: ButtonState::Released, .. } if *k == key)
+    }
+
+    /// True for keyboard, mouse and focus events, which replays substitute.
+    pub fn is_input(&self) -> bool {
+        !matches!(
+            self,
+            WinEvent::Close | WinEvent::Redraw | WinEvent::Resize(..) | WinEvent::Nothing
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ButtonState {
+    Pressed,
+    Released,
+}
+
+impl From<winit::event::ElementState> for ButtonState {
+    fn from(state: winit::event::ElementState) -> Self {
+        match state {
+            winit::event::ElementState::Pressed => ButtonState::Pressed,
+            winit::event::ElementState::Released => ButtonState::Released,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum MouseScroll {
     Line(f64),
     Pixel(f64),
@@ -29,3 +75,182 @@ pub enum MouseScroll {
 pub struct WinEvents {
     pub events: Vec<WinEvent>,
 }
+
+/// Typed event channel shared between systems.
+///
+/// Events are double buffered: they are kept for the step they are sent in
+/// and the next one, so every system with an `EventReader` sees each event
+/// once, whether it runs before or after the sender. Channels are registered
+/// with `State::add_event`, which also swaps the buffers after every step.
+pub struct Events<T> {
+    // events sent during the previous step, the first one has id `previous_start`
+    previous: Vec<T>,
+    previous_start: usize,
+    // events sent during the current step
+    current: Vec<T>,
+    current_start: usize,
+    event_count: usize,
+}
+
+impl<T> Default for Events<T> {
+    fn default() -> Self {
+        Self {
+            previous: Vec::new(),
+            previous_start: 0,
+            current: Vec::new(),
+            current_start: 0,
+            event_count: 0,
+        }
+    }
+}
+
+impl<T> Events<T> {
+    pub fn send(&mut self, event: T) {
+        self.current.push(event);
+        self.event_count += 1;
+    }
+
+    pub fn send_batch(&mut self, events: impl IntoIterator<Item = T>) {
+        for event in events {
+            self.send(event);
+        }
+    }
+
+    /// Drops the previous step's events, current ones become the previous.
+    pub fn update(&mut self) {
+        std::mem::swap(&mut self.previous, &mut self.current);
+        self.current.clear();
+        self.previous_start = self.current_start;
+        self.current_start = self.event_count;
+    }
+
+    pub fn clear(&mut self) {
+        self.previous.clear();
+        self.current.clear();
+        self.previous_start = self.event_count;
+        self.current_start = self.event_count;
+    }
+
+    /// Number of buffered events.
+    pub fn len(&self) -> usize {
+        self.previous.len() + self.current.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Iterates over every buffered event, ignoring readers' cursors.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.previous.iter().chain(self.current.iter())
+    }
+}
+
+/// Cursor over an `Events` channel, kept by a system between steps.
+pub struct EventReader<T> {
+    last: usize,
+    _marker: std::marker::PhantomData<fn() -> T>,
+}
+
+impl<T> Default for EventReader<T> {
+    fn default() -> Self {
+        Self {
+            last: 0,
+            _marker: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<T> EventReader<T> {
+    /// Iterates over the events sent since the last read.
+    pub fn read<'a>(&mut self, events: &'a Events<T>) -> impl Iterator<Item = &'a T> {
+        if self.last < events.previous_start {
+            log::warn!(
+                "event reader missed {} events",
+                events.previous_start - self.last
+            );
+        }
+
+        let previous = self.last.saturating_sub(events.previous_start);
+        let current = self.last.saturating_sub(events.current_start);
+        self.last = events.event_count;
+
+        events.previous[previous.min(events.previous.len())..]
+            .iter()
+            .chain(events.current[current.min(events.current.len())..].iter())
+    }
+
+    /// Skips the unread events.
+    pub fn clear(&mut self, events: &Events<T>) {
+        self.last = events.event_count;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(reader: &mut EventReader<u32>, events: &Events<u32>) -> Vec<u32> {
+        reader.read(events).copied().collect()
+    }
+
+    #[test]
+    fn events_expire_after_two_updates() {
+        let mut events = Events::default();
+        events.send(1);
+        events.update();
+        assert_eq!(events.iter().copied().collect::<Vec<_>>(), [1]);
+
+        events.update();
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn readers_see_each_event_once() {
+        let mut events = Events::default();
+        let mut reader = EventReader::default();
+        events.send_batch([1, 2]);
+        assert_eq!(read(&mut reader, &events), [1, 2]);
+        assert!(read(&mut reader, &events).is_empty());
+
+        events.update();
+        events.send(3);
+        assert_eq!(read(&mut reader, &events), [3]);
+        assert!(read(&mut reader, &events).is_empty());
+    }
+
+    #[test]
+    fn late_readers_see_the_previous_step() {
+        let mut events = Events::default();
+        let mut reader = EventReader::default();
+        events.send(1);
+        events.update();
+        events.send(2);
+
+        assert_eq!(read(&mut reader, &events), [1, 2]);
+    }
+
+    #[test]
+    fn readers_miss_expired_events() {
+        let mut events = Events::default();
+        let mut reader = EventReader::default();
+        events.send(1);
+        events.update();
+        events.send(2);
+        events.update();
+        events.send(3);
+
+        assert_eq!(read(&mut reader, &events), [2, 3]);
+    }
+
+    #[test]
+    fn cleared_readers_skip_unread_events() {
+        let mut events = Events::default();
+        let mut reader = EventReader::default();
+        events.send(1);
+        reader.clear(&events);
+        events.send(2);
+
+        assert_eq!(read(&mut reader, &events), [2]);
+    }
+}
@@ -1,7 +1,8 @@
-use super::event::{WinEvent, MouseScroll};
+use super::action::Actions;
 use super::camera::{self, CameraController};
-use std::time;
+use super::input;
 use cgmath::prelude::*;
+use std::time;
 
 #[derive(Default)]
 pub struct FlyCameraController {
@@ -15,6 +16,7 @@ pub struct FlyCameraController {
     pub rotate_vertical: f32,
     pub scroll: f32,
     pub speed: f32,
+    /// Radians turned per pixel of mouse motion.
     pub sensitivity: f32,
 }
 
@@ -34,14 +36,9 @@ impl FlyCameraController {
             sensitivity,
         }
     }
-    
-    fn clear_inputs(&mut self) {
-        self.amount_left = 0.0;
-        self.amount_right = 0.0;
-        self.amount_forward = 0.0;
-        self.amount_backward = 0.0;
-        self.amount_up = 0.0;
-        self.amount_down = 0.0;
+
+    // movement follows the held keys, mouse and scroll deltas are consumed once
+    fn clear_deltas(&mut self) {
         self.rotate_horizontal = 0.0;
         self.rotate_vertical = 0.0;
         self.scroll = 0.0;
@@ -49,59 +46,32 @@ impl FlyCameraController {
 }
 
 impl CameraController for FlyCameraController {
-    fn process_events(&mut self, event: &WinEvent) -> bool {
-        match event {
-            WinEvent::Space => {
-                self.amount_up = 1.0;
-                true
-            }
-            WinEvent::LShift => {
-                self.amount_down = 1.0;
-                true
-            }
-            WinEvent::Up => {
-                self.amount_forward = 1.0;
-                true
-            }
-            WinEvent::Left => {
-                self.amount_left = 1.0;
-                true
-            }
-            WinEvent::Down => {
-                self.amount_backward = 1.0;
-                true
-            }
-            WinEvent::Right => {
-                self.amount_right = 1.0;
-                true
-            }
-            WinEvent::MouseMotion(x, y) => {
-                self.process_mouse(*x, *y);
-                true
-            }
-            WinEvent::Scroll(MouseScroll::Line(dt)) => {
-                self.process_scroll(*dt * 100.0);
-                true
-            }
-            WinEvent::Scroll(MouseScroll::Pixel(dt)) => {
-                self.process_scroll(*dt);
-                true
-            }
-            _ => false,
-        }
+    /// Reads the "move_*" actions and the "look_x", "look_y" and "zoom" axes.
+    fn process_actions(&mut self, actions: &Actions) {
+        let amount = |action| if actions.is_pressed(action) { 1.0 } else { 0.0 };
+
+        self.amount_up = amount("move_up");
+        self.amount_down = amount("move_down");
+        self.amount_forward = amount("move_forward");
+        self.amount_left = amount("move_left");
+        self.amount_backward = amount("move_backward");
+        self.amount_right = amount("move_right");
+
+        self.process_mouse(actions.axis("look_x") as f64, actions.axis("look_y") as f64);
+        self.process_scroll(actions.axis("zoom") as f64 * input::PIXELS_PER_LINE);
     }
 
     fn process_mouse(&mut self, mouse_dx: f64, mouse_dy: f64) {
-        self.rotate_horizontal = mouse_dx as f32;
-        self.rotate_vertical = mouse_dy as f32;
+        self.rotate_horizontal += mouse_dx as f32;
+        self.rotate_vertical += mouse_dy as f32;
     }
 
     fn process_scroll(&mut self, dt: f64) {
-        self.scroll = dt as f32;
+        self.scroll += dt as f32;
     }
 
     fn update_camera(&mut self, camera: &mut camera::Camera, dt: time::Duration) {
-        use cgmath::{Vector3, Rad};
+        use cgmath::{Rad, Vector3};
 
         let dt = dt.as_secs_f32();
 
@@ -117,24 +87,54 @@ impl CameraController for FlyCameraController {
         // changes when zooming. I've added this to make it easier
         // to get closer to an object you want to focus on.
         let (pitch_sin, pitch_cos) = camera.pitch.0.sin_cos();
-        let scrollward = Vector3::new(pitch_cos * yaw_cos, pitch_sin, pitch_cos * yaw_sin).normalize();
-        camera.position += scrollward * self.scroll * self.speed * self.sensitivity * dt;
+        let scrollward =
+            Vector3::new(pitch_cos * yaw_cos, pitch_sin, pitch_cos * yaw_sin).normalize();
+        camera.position += scrollward * self.scroll * self.speed * self.sensitivity;
 
         // Move up/down. Since we don't use roll, we can just
         // modify the y coordinate directly.
         camera.position.y += (self.amount_up - self.amount_down) * self.speed * dt;
 
-        // Rotate
-        camera.yaw += Rad(self.rotate_horizontal) * self.sensitivity * dt;
-        camera.pitch += Rad(-self.rotate_vertical) * self.sensitivity * dt;
+        // Rotate, mouse motion is already a distance so it isn't scaled by dt
+        camera.yaw += Rad(self.rotate_horizontal) * self.sensitivity;
+        camera.pitch += Rad(-self.rotate_vertical) * self.sensitivity;
 
         // Keep the camera's angle from going too high/low.
-        if camera.pitch < -Rad(Self::SAFE_FRAC_PI_2) {
-            camera.pitch = -Rad(Self::SAFE_FRAC_PI_2);
-        } else if camera.pitch > Rad(Self::SAFE_FRAC_PI_2) {
-            camera.pitch = Rad(Self::SAFE_FRAC_PI_2);
+        if camera.pitch < -Rad(camera::SAFE_FRAC_PI_2) {
+            camera.pitch = -Rad(camera::SAFE_FRAC_PI_2);
+        } else if camera.pitch > Rad(camera::SAFE_FRAC_PI_2) {
+            camera.pitch = Rad(camera::SAFE_FRAC_PI_2);
         }
 
-        self.clear_inputs();
+        self.clear_deltas();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::Deg;
+
+    // turns by `steps` mouse deltas per frame, `frames` times, returning the yaw
+    fn look(frames: u32, steps: u32, dx: f64) -> f32 {
+        let mut controller = FlyCameraController::new(10.0, 0.004);
+        let mut camera = camera::Camera::new((0.0, 0.0, 0.0), Deg(0.0), Deg(0.0));
+        let frame_time = time::Duration::from_secs_f32(1.0 / frames as f32);
+        for _ in 0..frames {
+            for _ in 0..steps {
+                controller.process_mouse(dx, 0.0);
+            }
+            controller.update_camera(&mut camera, frame_time);
+        }
+        camera.yaw.0
+    }
+
+    #[test]
+    fn look_speed_does_not_depend_on_the_step_rate() {
+        let yaw = look(1, 1, 240.0);
+        assert!((yaw - 0.96).abs() < 1e-5, "{}", yaw);
+        assert!((look(1, 4, 60.0) - yaw).abs() < 1e-5);
+        assert!((look(4, 1, 60.0) - yaw).abs() < 1e-5);
+        assert!((look(6, 2, 20.0) - yaw).abs() < 1e-5);
     }
 }
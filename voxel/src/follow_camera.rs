@@ -0,0 +1,176 @@
+use super::action::Actions;
+use super::camera::{self, CameraController, CameraWorld};
+use super::input;
+use cgmath::{prelude::*, Point3, Rad, Vector3};
+use std::time;
+
+/// Third person camera following an entity's actor.
+///
+/// The camera sits `distance` away from the pivot, the target's position
+/// plus `pivot_offset`, at the `yaw` and `pitch` angles which the look axes
+/// orbit. With physics, a ray cast from the pivot pulls the camera in front
+/// of any collider in the way.
+pub struct FollowCameraController {
+    pub target: Option<specs::Entity>,
+    /// Point orbited and looked at, relative to the target.
+    pub pivot_offset: Vector3<f32>,
+    pub distance: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    pub yaw: Rad<f32>,
+    pub pitch: Rad<f32>,
+    /// How fast the camera catches up with the target, 0 disables smoothing.
+    pub damping: f32,
+    /// Seconds of the target's velocity added to the point looked at.
+    pub look_ahead: f32,
+    /// Radians turned per pixel of mouse motion.
+    pub sensitivity: f32,
+    /// Distance covered per scrolled line.
+    pub zoom_speed: f32,
+    /// Orbits only while this action is held, always if None.
+    pub orbit_action: Option<String>,
+    /// Space left between the camera and the collider blocking the view.
+    pub collision_margin: f32,
+    target_position: Option<Point3<f32>>,
+    target_velocity: Vector3<f32>,
+    // distance to the first collider between the pivot and the camera
+    obstruction: Option<f32>,
+    rotate_horizontal: f32,
+    rotate_vertical: f32,
+    scroll: f32,
+}
+
+impl FollowCameraController {
+    /// `offset` is the camera position relative to the pivot before any orbiting.
+    pub fn new<V: Into<Vector3<f32>>>(target: specs::Entity, offset: V) -> Self {
+        let mut this = Self {
+            target: Some(target),
+            pivot_offset: Vector3::new(0.0, 1.0, 0.0),
+            distance: 0.0,
+            min_distance: 1.0,
+            max_distance: 50.0,
+            yaw: Rad(0.0),
+            pitch: Rad(0.0),
+            damping: 8.0,
+            look_ahead: 0.3,
+            sensitivity: 0.005,
+            zoom_speed: 1.0,
+            orbit_action: None,
+            collision_margin: 0.2,
+            target_position: None,
+            target_velocity: Vector3::zero(),
+            obstruction: None,
+            rotate_horizontal: 0.0,
+            rotate_vertical: 0.0,
+            scroll: 0.0,
+        };
+        this.set_offset(offset.into());
+        this
+    }
+
+    /// Places the camera at `offset` from the pivot.
+    pub fn set_offset(&mut self, offset: Vector3<f32>) {
+        self.distance = offset.magnitude();
+        if self.distance > f32::EPSILON {
+            self.yaw = Rad(offset.z.atan2(offset.x));
+            self.pitch = Rad((offset.y / self.distance).asin());
+        }
+    }
+
+    pub fn set_target(&mut self, target: Option<specs::Entity>) {
+        self.target = target;
+        self.target_position = None;
+        self.target_velocity = Vector3::zero();
+    }
+
+    fn direction(&self) -> Vector3<f32> {
+        let (sin_pitch, cos_pitch) = self.pitch.0.sin_cos();
+        let (sin_yaw, cos_yaw) = self.yaw.0.sin_cos();
+
+        Vector3::new(cos_pitch * cos_yaw, sin_pitch, cos_pitch * sin_yaw)
+    }
+}
+
+impl CameraController for FollowCameraController {
+    /// Reads the "look_x", "look_y" and "zoom" axes.
+    fn process_actions(&mut self, actions: &Actions) {
+        let orbiting = match &self.orbit_action {
+            Some(action) => actions.is_pressed(action),
+            None => true,
+        };
+        if orbiting {
+            self.process_mouse(actions.axis("look_x") as f64, actions.axis("look_y") as f64);
+        }
+        self.process_scroll(actions.axis("zoom") as f64 * input::PIXELS_PER_LINE);
+    }
+
+    fn process_world(&mut self, world: &CameraWorld) {
+        let target = match self.target {
+            Some(target) => target,
+            None => return,
+        };
+        let actor = match world.actors.get(target) {
+            Some(actor) => actor,
+            None => return,
+        };
+
+        let position = Point3::from_vec(actor.transform.position);
+        if let (Some(previous), false) = (self.target_position, world.dt.is_zero()) {
+            self.target_velocity = (position - previous) / world.dt.as_secs_f32();
+        }
+        self.target_position = Some(position);
+
+        let pivot = position + self.pivot_offset;
+        let exclude = world.rigid_bodies.get(target).map(|rb| rb.rigid_body);
+        self.obstruction = world
+            .physics
+            .and_then(|physics| physics.cast_ray(pivot, self.direction(), self.distance, exclude));
+    }
+
+    fn process_mouse(&mut self, mouse_dx: f64, mouse_dy: f64) {
+        self.rotate_horizontal += mouse_dx as f32;
+        self.rotate_vertical += mouse_dy as f32;
+    }
+
+    fn process_scroll(&mut self, dt: f64) {
+        self.scroll += (dt / input::PIXELS_PER_LINE) as f32;
+    }
+
+    fn update_camera(&mut self, camera: &mut camera::Camera, dt: time::Duration) {
+        let dt = dt.as_secs_f32();
+
+        self.yaw += Rad(self.rotate_horizontal) * self.sensitivity;
+        self.pitch += Rad(self.rotate_vertical) * self.sensitivity;
+        self.pitch.0 = self
+            .pitch
+            .0
+            .clamp(-camera::SAFE_FRAC_PI_2, camera::SAFE_FRAC_PI_2);
+        self.distance = (self.distance - self.scroll * self.zoom_speed)
+            .clamp(self.min_distance, self.max_distance);
+        self.rotate_horizontal = 0.0;
+        self.rotate_vertical = 0.0;
+        self.scroll = 0.0;
+
+        let target = match self.target_position {
+            Some(target) => target,
+            None => return,
+        };
+        let pivot = target + self.pivot_offset;
+
+        let distance = match self.obstruction {
+            Some(hit) => (hit - self.collision_margin).clamp(0.0, self.distance),
+            None => self.distance,
+        };
+        let desired = pivot + self.direction() * distance;
+
+        // snap in front of obstacles, smoothing would clip through them
+        if self.damping > 0.0 && self.obstruction.is_none() {
+            let t = 1.0 - (-self.damping * dt).exp();
+            camera.position += (desired - camera.position) * t;
+        } else {
+            camera.position = desired;
+        }
+
+        camera.look_at(pivot + self.target_velocity * self.look_ahead);
+    }
+}
@@ -0,0 +1,195 @@
+use std::time;
+
+/// Simulation clock advanced by the game ticker on every fixed step.
+///
+/// Real time is the monotonic time since the clock was created, unscaled time
+/// advances one fixed step per simulation step no matter the pause or scale,
+/// and game time is the scaled time systems see through `DeltaTime`.
+pub struct GameClock {
+    started: instant::Instant,
+    time_scale: f32,
+    paused: bool,
+    // steps to run while paused, used to step frame by frame
+    pending_frames: u32,
+    frame: u64,
+    game_time: time::Duration,
+    unscaled_time: time::Duration,
+    delta: time::Duration,
+    unscaled_delta: time::Duration,
+}
+
+impl Default for GameClock {
+    fn default() -> Self {
+        Self {
+            started: instant::Instant::now(),
+            time_scale: 1.0,
+            paused: false,
+            pending_frames: 0,
+            frame: 0,
+            game_time: time::Duration::ZERO,
+            unscaled_time: time::Duration::ZERO,
+            delta: time::Duration::ZERO,
+            unscaled_delta: time::Duration::ZERO,
+        }
+    }
+}
+
+impl GameClock {
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+        self.pending_frames = 0;
+    }
+
+    pub fn toggle_pause(&mut self) {
+        if self.paused {
+            self.resume();
+        } else {
+            self.pause();
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Scales game time, 0.5 is slow motion and 2.0 runs twice as fast.
+    pub fn set_time_scale(&mut self, time_scale: f32) {
+        self.time_scale = time_scale.max(0.0);
+    }
+
+    pub fn time_scale(&self) -> f32 {
+        self.time_scale
+    }
+
+    /// Lets `frames` simulation steps advance game time while paused.
+    pub fn step_frames(&mut self, frames: u32) {
+        self.pending_frames += frames;
+    }
+
+    /// Monotonic time since the clock was created, always moving.
+    pub fn real_time(&self) -> time::Duration {
+        self.started.elapsed()
+    }
+
+    /// Scaled time that stops while paused.
+    pub fn game_time(&self) -> time::Duration {
+        self.game_time
+    }
+
+    /// Simulation time ignoring pause and time scale.
+    pub fn unscaled_time(&self) -> time::Duration {
+        self.unscaled_time
+    }
+
+    /// Game time advanced by the last step.
+    pub fn delta(&self) -> time::Duration {
+        self.delta
+    }
+
+    /// Unscaled time advanced by the last step.
+    pub fn unscaled_delta(&self) -> time::Duration {
+        self.unscaled_delta
+    }
+
+    /// Number of simulation steps run so far.
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Advances the clock by one simulation step and returns the game delta time.
+    pub fn advance(&mut self, step: time::Duration) -> time::Duration {
+        self.frame += 1;
+        self.unscaled_delta = step;
+        self.unscaled_time += step;
+
+        let running = if self.paused && self.pending_frames > 0 {
+            self.pending_frames -= 1;
+            true
+        } else {
+            !self.paused
+        };
+
+        self.delta = if running {
+            step.mul_f32(self.time_scale)
+        } else {
+            time::Duration::ZERO
+        };
+        self.game_time += self.delta;
+
+        self.delta
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STEP: time::Duration = time::Duration::from_millis(10);
+
+    #[test]
+    fn advances_by_the_step() {
+        let mut clock = GameClock::default();
+        assert_eq!(clock.advance(STEP), STEP);
+        clock.advance(STEP);
+
+        assert_eq!(clock.frame(), 2);
+        assert_eq!(clock.game_time(), STEP * 2);
+        assert_eq!(clock.unscaled_time(), STEP * 2);
+    }
+
+    #[test]
+    fn pause_stops_game_time_only() {
+        let mut clock = GameClock::default();
+        clock.advance(STEP);
+        clock.pause();
+        assert_eq!(clock.advance(STEP), time::Duration::ZERO);
+
+        assert!(clock.is_paused());
+        assert_eq!(clock.game_time(), STEP);
+        assert_eq!(clock.unscaled_time(), STEP * 2);
+        assert_eq!(clock.unscaled_delta(), STEP);
+
+        clock.resume();
+        clock.advance(STEP);
+        assert_eq!(clock.game_time(), STEP * 2);
+    }
+
+    #[test]
+    fn step_frames_runs_while_paused() {
+        let mut clock = GameClock::default();
+        clock.pause();
+        clock.step_frames(2);
+
+        assert_eq!(clock.advance(STEP), STEP);
+        assert_eq!(clock.advance(STEP), STEP);
+        assert_eq!(clock.advance(STEP), time::Duration::ZERO);
+        assert_eq!(clock.game_time(), STEP * 2);
+    }
+
+    #[test]
+    fn resume_drops_pending_frames() {
+        let mut clock = GameClock::default();
+        clock.pause();
+        clock.step_frames(3);
+        clock.resume();
+        clock.pause();
+
+        assert_eq!(clock.advance(STEP), time::Duration::ZERO);
+    }
+
+    #[test]
+    fn time_scale_scales_game_time() {
+        let mut clock = GameClock::default();
+        clock.set_time_scale(0.5);
+        assert_eq!(clock.advance(STEP), STEP / 2);
+        assert_eq!(clock.unscaled_delta(), STEP);
+
+        clock.set_time_scale(-1.0);
+        assert_eq!(clock.time_scale(), 0.0);
+        assert_eq!(clock.advance(STEP), time::Duration::ZERO);
+    }
+}
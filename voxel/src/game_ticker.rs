@@ -1,97 +1,388 @@
-use specs::WorldExt;
+use std::collections::HashMap;
+use std::time;
 
-use crate::{actor, camera, delta_time, event, state::State, ticker};
+use specs::{Join, WorldExt};
 
-pub struct GameTicker {}
+use crate::{
+    action, actor, camera, delta_time, error, event, game_clock, input, profiler,
+    renderer::render::{ActorInstance, View},
+    replay,
+    state::State,
+    ticker,
+};
+
+pub struct GameTicker {
+    fixed_step: time::Duration,
+    max_steps: u32,
+    accumulator: time::Duration,
+    last_tick: Option<instant::Instant>,
+    // cameras move by the time between redraws, not between window events
+    last_draw: Option<instant::Instant>,
+    // window events waiting for the next simulation step
+    pending_events: Vec<event::WinEvent>,
+    // actors' transforms before the last step, used to interpolate rendering
+    previous_transforms: HashMap<specs::Entity, actor::transform::Transform>,
+    recorder: Option<replay::Recorder>,
+    // replaces live input until the recording runs out
+    player: Option<replay::Player>,
+}
+
+impl Default for GameTicker {
+    fn default() -> Self {
+        Self {
+            fixed_step: time::Duration::from_secs_f64(1.0 / 60.0),
+            max_steps: 5,
+            accumulator: time::Duration::ZERO,
+            last_tick: None,
+            last_draw: None,
+            pending_events: Vec::new(),
+            previous_transforms: HashMap::new(),
+            recorder: None,
+            player: None,
+        }
+    }
+}
 
 impl GameTicker {
-    fn draw(
-        &mut self,
-        global_state: &mut State,
-        events: &Vec<event::WinEvent>,
-        actors: &Vec<actor::Actor>,
-    ) {
+    /// Fails if `fixed_step` or `max_steps` is zero.
+    pub fn new(fixed_step: time::Duration, max_steps: u32) -> error::Result<Self> {
+        let mut this = Self::default();
+        this.set_fixed_step(fixed_step)?;
+        this.set_max_steps(max_steps)?;
+
+        Ok(this)
+    }
+
+    /// Simulation step, systems always see it as their delta time.
+    pub fn fixed_step(&self) -> time::Duration {
+        self.fixed_step
+    }
+
+    /// Fails if `fixed_step` is zero.
+    pub fn set_fixed_step(&mut self, fixed_step: time::Duration) -> error::Result<()> {
+        if fixed_step.is_zero() {
+            return Err(error::Error::InvalidConfig("fixed step must not be zero"));
+        }
+        self.fixed_step = fixed_step;
+
+        Ok(())
+    }
+
+    /// Maximum steps run in a single tick while catching up, late time beyond it is dropped.
+    pub fn max_steps(&self) -> u32 {
+        self.max_steps
+    }
+
+    /// Fails if `max_steps` is zero, the simulation would never advance.
+    pub fn set_max_steps(&mut self, max_steps: u32) -> error::Result<()> {
+        if max_steps == 0 {
+            return Err(error::Error::InvalidConfig("max steps must not be zero"));
+        }
+        self.max_steps = max_steps;
+
+        Ok(())
+    }
+
+    /// Records the input and frame time of every following tick.
+    pub fn record(&mut self, recorder: replay::Recorder) {
+        self.recorder = Some(recorder);
+    }
+
+    /// Replays a recording in place of live input, using its step settings.
+    /// Live input is used again once every recorded frame has been replayed.
+    pub fn play(&mut self, player: replay::Player) -> error::Result<()> {
+        self.set_fixed_step(player.recording().fixed_step)?;
+        self.set_max_steps(player.recording().max_steps)?;
+        self.player = Some(player);
+
+        Ok(())
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        self.player.is_some()
+    }
+
+    /// Writes the input recorded so far, e.g. before bailing out on an error.
+    pub fn save_recording(&mut self) {
+        if let Some(recorder) = self.recorder.as_mut() {
+            recorder.save();
+        }
+    }
+
+    /// Saves the recording and the profiler trace once the app stops running.
+    pub fn finish(&mut self, global_state: &State) {
+        if let Some(mut recorder) = self.recorder.take() {
+            recorder.finish();
+        }
+        global_state
+            .world
+            .read_resource::<profiler::Profiler>()
+            .finish();
+    }
+
+    /// Interpolation factor between the previous and the current simulation step.
+    pub fn alpha(&self) -> f32 {
+        self.accumulator.as_secs_f32() / self.fixed_step.as_secs_f32()
+    }
+
+    /// Time left until the next simulation step is due.
+    pub fn remaining(&self) -> time::Duration {
+        self.fixed_step.saturating_sub(self.accumulator)
+    }
+
+    /// Runs exactly one simulation step, regardless of the elapsed time.
+    pub fn step(&mut self, global_state: &mut State) -> error::Result<()> {
+        if !global_state.is_headless() {
+            let start = instant::Instant::now();
+            self.snapshot_transforms(global_state);
+            global_state.profile("snapshot", start);
+        }
+
+        let events = std::mem::take(&mut self.pending_events);
+        global_state
+            .world
+            .entry::<input::InputState>()
+            .or_insert_with(Default::default)
+            .update(&events);
+        if let Some(mut actions) = global_state.world.try_fetch_mut::<action::Actions>() {
+            actions.update(&global_state.world.read_resource::<input::InputState>());
+        }
+        global_state
+            .world
+            .entry::<event::WinEvents>()
+            .or_insert_with(Default::default)
+            .events = events;
+        let dt = global_state
+            .world
+            .entry::<game_clock::GameClock>()
+            .or_insert_with(Default::default)
+            .advance(self.fixed_step);
+        global_state
+            .world
+            .entry::<delta_time::DeltaTime>()
+            .or_insert_with(Default::default)
+            .advance(dt);
+
+        let start = instant::Instant::now();
+        global_state.dispatch();
+        global_state.profile("dispatch", start);
+
+        let start = instant::Instant::now();
+        global_state.world.maintain();
+        global_state.update_events();
+        global_state.profile("maintain", start);
+
+        global_state
+            .world
+            .write_resource::<event::WinEvents>()
+            .events
+            .clear();
+
+        let start = instant::Instant::now();
+        let result = global_state.apply_scene_transitions();
+        global_state.profile("scene_transitions", start);
+
+        result
+    }
+
+    fn snapshot_transforms(&mut self, global_state: &State) {
+        let entities = global_state.world.entities();
+        let actors = global_state.world.read_storage::<actor::Actor>();
+
+        self.previous_transforms.clear();
+        for (entity, actor) in (&entities, &actors).join() {
+            self.previous_transforms.insert(entity, actor.transform);
+        }
+    }
+
+    // actors as drawn, their meshes borrowed from `actors` and their transforms
+    // interpolated between the last two steps
+    fn interpolated_actors<'s>(
+        &self,
+        entities: &specs::Entities,
+        actors: &'s specs::ReadStorage<actor::Actor>,
+    ) -> Vec<ActorInstance<'s>> {
+        let alpha = self.alpha();
+
+        (entities, actors)
+            .join()
+            .map(|(entity, actor)| ActorInstance {
+                mesh: &actor.model.mesh,
+                transform: match self.previous_transforms.get(&entity) {
+                    Some(previous) => previous.interpolate(&actor.transform, alpha),
+                    None => actor.transform,
+                },
+                color: actor.model.color,
+            })
+            .collect()
+    }
+
+    fn draw(&mut self, global_state: &mut State, events: &[event::WinEvent]) -> error::Result<()> {
         use event::WinEvent::*;
 
-        let mut camera = global_state.world.write_resource::<camera::CameraBundle>();
-        let dt = global_state.world.read_resource::<delta_time::DeltaTime>();
+        let render = match global_state.render.as_mut() {
+            Some(render) => render,
+            None => return Ok(()),
+        };
+        let mut main_camera = global_state.world.try_fetch_mut::<camera::CameraBundle>();
+        let mut cameras = global_state.world.write_storage::<camera::CameraBundle>();
+        let profiler = global_state.world.read_resource::<profiler::Profiler>();
+        let entities = global_state.world.entities();
+        let actors = global_state.world.read_storage::<actor::Actor>();
 
         for ev in events.iter() {
             log::trace!("render system processing {:?}", ev);
 
             match ev {
                 Redraw => {
-                    camera.update(dt.dt);
+                    let start = instant::Instant::now();
+                    let frame_time = self
+                        .last_draw
+                        .replace(start)
+                        .map(|last| start - last)
+                        .unwrap_or_default();
+                    let mut bundles: Vec<&mut camera::CameraBundle> = main_camera
+                        .as_deref_mut()
+                        .into_iter()
+                        .chain((&mut cameras).join())
+                        .collect();
+                    // stable, the main camera goes first among equal priorities
+                    bundles.sort_by_key(|camera| camera.priority);
 
-                    let res = global_state.render.draw(
-                        actors,
-                        &camera.build_bind_group(&global_state.render.device),
-                    );
+                    for camera in bundles.iter_mut() {
+                        if let Some(size) = render.target_size(&camera.target) {
+                            camera.projection.resize(camera.viewport.pixel_size(size));
+                        }
+                        camera.update(frame_time);
+                        camera.write_uniform(render);
+                    }
+                    let views: Vec<View> = bundles
+                        .iter()
+                        .filter_map(|camera| {
+                            Some(View {
+                                camera_bg: camera.bind_group()?,
+                                frustum: camera.uniform.frustum(),
+                                viewport: camera.viewport,
+                                target: &camera.target,
+                            })
+                        })
+                        .collect();
+                    profiler.record("camera", start);
+
+                    let start = instant::Instant::now();
+                    let instances = self.interpolated_actors(&entities, &actors);
+                    profiler.record("interpolate", start);
+
+                    let res = render.draw(&instances, &views, &profiler);
                     match res {
                         Ok(_) => {}
                         // Reconfigure the surface if it's lost or outdated
                         Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
                             log::warn!("wgpu surface lost or outdated");
-                            let size = global_state.render.size.clone();
-                            global_state.render.resize(size);
+                            let size = render.size;
+                            render.resize(size);
                         }
-                        // The system is out of memory, we should probably quit
+                        // The system is out of memory, the app shuts down
                         Err(wgpu::SurfaceError::OutOfMemory) => {
-                            log::warn!("wgpu surface out of memory");
-                            // TODO
-                            // *win.should_exit.lock().unwrap() = true
-                            panic!("wgpu surface out of memory");
+                            log::error!("wgpu surface out of memory");
+                            return Err(error::Error::SurfaceOutOfMemory);
                         }
                         // We're ignoring timeouts
                         Err(wgpu::SurfaceError::Timeout) => log::warn!("surface timeout"),
                     }
                 }
-                Resize(w, h) => global_state.render.resize((*w, *h)),
+                Resize(w, h) => render.resize((*w, *h)),
                 _ => {
                     // log::trace!("calling rendeder_engine input");
                 }
             }
         }
+
+        Ok(())
     }
 }
 
 impl ticker::Ticker for GameTicker {
-    fn tick(&mut self, global_state: &mut State, win_events: Vec<event::WinEvent>) {
+    fn tick(
+        &mut self,
+        global_state: &mut State,
+        win_events: Vec<event::WinEvent>,
+    ) -> error::Result<()> {
         log::trace!("running tick for game ticker");
 
-        let actors: Vec<actor::Actor> = global_state
-            .world
-            .read_component::<actor::Actor>()
-            .as_slice()
-            .iter()
-            .cloned()
-            .collect();
+        let now = instant::Instant::now();
+        let mut frame_time = self.last_tick.map(|last| now - last).unwrap_or_default();
+        self.last_tick = Some(now);
 
-        self.draw(global_state, &win_events, &actors);
+        let mut win_events = win_events;
+        if let Some(player) = self.player.as_mut() {
+            match player.next_frame() {
+                Some(frame) => {
+                    // recorded input replaces the live one, the window's own events still apply
+                    frame_time = frame.frame_time;
+                    let live = win_events.into_iter().filter(|event| !event.is_input());
+                    win_events = frame
+                        .events
+                        .iter()
+                        .filter(|event| event.is_input())
+                        .cloned()
+                        .chain(live)
+                        .collect();
+                }
+                None => {
+                    log::info!("replay finished, back to live input");
+                    self.player = None;
+                }
+            }
+        }
+        if let Some(recorder) = self.recorder.as_mut() {
+            recorder.record(frame_time, &win_events);
+        }
 
-        global_state
-            .world
-            .write_resource::<event::WinEvents>()
-            .events = win_events;
-
-        let mut dispatcher_builder =
-            specs::DispatcherBuilder::new().with_pool(global_state.ecs_thread_pool.clone());
-        dispatcher_builder = global_state.setup_global_system(dispatcher_builder);
-        dispatcher_builder = global_state
-            .scene
-            .as_mut()
-            .unwrap()
-            .setup_systems(dispatcher_builder);
-        let mut dispatcher = dispatcher_builder.build();
-        dispatcher.setup(&mut global_state.world);
-        dispatcher.dispatch(&global_state.world);
+        self.accumulator += frame_time;
+        self.pending_events.extend(win_events.iter().cloned());
 
-        global_state.world.maintain();
+        let mut steps = 0;
+        while self.accumulator >= self.fixed_step && steps < self.max_steps {
+            self.step(global_state)?;
+            self.accumulator -= self.fixed_step;
+            steps += 1;
+            // without rendering, a profiler frame is a simulation step
+            if global_state.is_headless() {
+                end_frame(global_state);
+            }
+        }
 
-        global_state
-            .world
-            .write_resource::<event::WinEvents>()
-            .events
-            .clear();
+        if self.accumulator >= self.fixed_step {
+            log::warn!(
+                "game ticker is running behind, dropping {:?}",
+                self.accumulator
+            );
+            let late = self.accumulator.as_nanos() % self.fixed_step.as_nanos();
+            self.accumulator = time::Duration::from_nanos(late as u64);
+        }
+
+        if !global_state.is_headless() {
+            let start = instant::Instant::now();
+            self.draw(global_state, &win_events)?;
+            global_state.profile("draw", start);
+
+            // ticks run on every window event, a profiler frame ends once drawn
+            if win_events
+                .iter()
+                .any(|event| matches!(event, event::WinEvent::Redraw))
+            {
+                end_frame(global_state);
+            }
+        }
+
+        Ok(())
     }
 }
+
+fn end_frame(global_state: &State) {
+    global_state
+        .world
+        .write_resource::<profiler::Profiler>()
+        .end_frame();
+}
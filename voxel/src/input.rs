@@ -0,0 +1,200 @@
+use std::collections::HashSet;
+
+use crate::event::{ButtonState, Key, Modifiers, MouseButton, MouseScroll, WinEvent};
+use crate::{action, cursor, error, event, plugin, state};
+
+/// Pixels scrolled by a single wheel line.
+pub const PIXELS_PER_LINE: f64 = 100.0;
+
+/// Keyboard and mouse state, updated by the game ticker before every step.
+///
+/// `just_pressed` and `just_released` hold for the step that received the
+/// event, mouse and scroll deltas are the sums since the previous step.
+pub struct InputState {
+    keys: HashSet<Key>,
+    just_pressed_keys: HashSet<Key>,
+    just_released_keys: HashSet<Key>,
+    buttons: HashSet<MouseButton>,
+    just_pressed_buttons: HashSet<MouseButton>,
+    just_released_buttons: HashSet<MouseButton>,
+    modifiers: Modifiers,
+    mouse_delta: (f64, f64),
+    scroll_delta: f64,
+    cursor_position: Option<(f64, f64)>,
+    focused: bool,
+}
+
+impl Default for InputState {
+    fn default() -> Self {
+        Self {
+            keys: HashSet::new(),
+            just_pressed_keys: HashSet::new(),
+            just_released_keys: HashSet::new(),
+            buttons: HashSet::new(),
+            just_pressed_buttons: HashSet::new(),
+            just_released_buttons: HashSet::new(),
+            modifiers: Modifiers::empty(),
+            mouse_delta: (0.0, 0.0),
+            scroll_delta: 0.0,
+            cursor_position: None,
+            focused: true,
+        }
+    }
+}
+
+impl InputState {
+    /// Starts a new step and applies the events received since the previous one.
+    pub fn update(&mut self, events: &[WinEvent]) {
+        self.just_pressed_keys.clear();
+        self.just_released_keys.clear();
+        self.just_pressed_buttons.clear();
+        self.just_released_buttons.clear();
+        self.mouse_delta = (0.0, 0.0);
+        self.scroll_delta = 0.0;
+
+        for ev in events {
+            self.process_event(ev);
+        }
+    }
+
+    fn process_event(&mut self, event: &WinEvent) {
+        match event {
+            WinEvent::Key {
+                key: Some(key),
+                state,
+                modifiers,
+                ..
+            } => {
+                self.modifiers = *modifiers;
+                match state {
+                    // key repeats are not new presses
+                    ButtonState::Pressed if self.keys.insert(*key) => {
+                        self.just_pressed_keys.insert(*key);
+                    }
+                    ButtonState::Released if self.keys.remove(key) => {
+                        self.just_released_keys.insert(*key);
+                    }
+                    _ => {}
+                }
+            }
+            WinEvent::MouseButton {
+                button,
+                state,
+                modifiers,
+            } => {
+                self.modifiers = *modifiers;
+                match state {
+                    ButtonState::Pressed if self.buttons.insert(*button) => {
+                        self.just_pressed_buttons.insert(*button);
+                    }
+                    ButtonState::Released if self.buttons.remove(button) => {
+                        self.just_released_buttons.insert(*button);
+                    }
+                    _ => {}
+                }
+            }
+            WinEvent::MouseMotion(dx, dy) => {
+                self.mouse_delta.0 += dx;
+                self.mouse_delta.1 += dy;
+            }
+            WinEvent::Scroll(MouseScroll::Line(lines)) => self.scroll_delta += lines,
+            WinEvent::Scroll(MouseScroll::Pixel(pixels)) => {
+                self.scroll_delta += pixels / PIXELS_PER_LINE
+            }
+            WinEvent::CursorMoved(x, y) => self.cursor_position = Some((*x, *y)),
+            WinEvent::CursorLeft => self.cursor_position = None,
+            WinEvent::ModifiersChanged(modifiers) => self.modifiers = *modifiers,
+            WinEvent::Focused(focused) => {
+                self.focused = *focused;
+                // releases never arrive once the window lost focus
+                if !focused {
+                    self.release_all();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn release_all(&mut self) {
+        self.just_released_keys.extend(self.keys.drain());
+        self.just_released_buttons.extend(self.buttons.drain());
+        self.modifiers = Modifiers::empty();
+    }
+
+    pub fn is_pressed(&self, key: Key) -> bool {
+        self.keys.contains(&key)
+    }
+
+    pub fn just_pressed(&self, key: Key) -> bool {
+        self.just_pressed_keys.contains(&key)
+    }
+
+    pub fn just_released(&self, key: Key) -> bool {
+        self.just_released_keys.contains(&key)
+    }
+
+    pub fn pressed_keys(&self) -> impl Iterator<Item = &Key> {
+        self.keys.iter()
+    }
+
+    pub fn is_mouse_pressed(&self, button: MouseButton) -> bool {
+        self.buttons.contains(&button)
+    }
+
+    pub fn mouse_just_pressed(&self, button: MouseButton) -> bool {
+        self.just_pressed_buttons.contains(&button)
+    }
+
+    pub fn mouse_just_released(&self, button: MouseButton) -> bool {
+        self.just_released_buttons.contains(&button)
+    }
+
+    pub fn modifiers(&self) -> Modifiers {
+        self.modifiers
+    }
+
+    /// Raw mouse motion since the previous step.
+    pub fn mouse_delta(&self) -> (f64, f64) {
+        self.mouse_delta
+    }
+
+    /// Wheel lines scrolled since the previous step, pixel deltas are converted.
+    pub fn scroll_delta(&self) -> f64 {
+        self.scroll_delta
+    }
+
+    /// None while the cursor is outside the window.
+    pub fn cursor_position(&self) -> Option<(f64, f64)> {
+        self.cursor_position
+    }
+
+    pub fn is_focused(&self) -> bool {
+        self.focused
+    }
+}
+
+/// Makes window input available to systems through the `WinEvents`,
+/// `InputState` and `Actions` resources, and the cursor controllable through `Cursor`.
+#[derive(Default)]
+pub struct InputPlugin {
+    pub bindings: action::Bindings,
+}
+
+impl InputPlugin {
+    pub fn new(bindings: action::Bindings) -> Self {
+        Self { bindings }
+    }
+}
+
+impl plugin::Plugin for InputPlugin {
+    fn setup(&mut self, global_state: &mut state::State) -> error::Result<()> {
+        global_state.world.insert(event::WinEvents::default());
+        global_state.world.insert(InputState::default());
+        global_state.world.insert(cursor::Cursor::default());
+        global_state
+            .world
+            .insert(action::Actions::new(std::mem::take(&mut self.bindings)));
+
+        Ok(())
+    }
+}
@@ -1,17 +1,40 @@
+pub mod action;
 pub mod actor;
 pub mod app;
+pub mod bounds;
 pub mod camera;
+pub mod camera_path;
+pub mod cursor;
 pub mod delta_time;
 pub mod ecs;
+pub mod error;
 pub mod event;
 pub mod fly_camera;
+pub mod follow_camera;
+pub mod game_clock;
 pub mod game_ticker;
+pub mod input;
+pub mod orbit_camera;
 pub mod physics;
+pub mod picking;
+pub mod plugin;
+pub mod profiler;
 pub mod renderer;
+pub mod replay;
+pub mod rng;
+pub mod ron_file;
 pub mod scene;
 pub mod state;
 pub mod ticker;
+pub mod timer;
 
-pub fn run(game: Box<dyn scene::Scene>) {
-    app::App::new(game).run();
+pub use app::{App, AppBuilder};
+pub use error::{Error, Result};
+
+pub fn run(game: Box<dyn scene::Scene>) -> Result<()> {
+    app::App::new(game)?.run()
+}
+
+pub fn run_headless(game: Box<dyn scene::Scene>) -> Result<()> {
+    app::App::headless(game)?.run()
 }
@@ -0,0 +1,124 @@
+use super::action::Actions;
+use super::camera::{self, CameraController};
+use super::input;
+use cgmath::{Point3, Rad, Vector3};
+use std::time;
+
+/// Orbits around a target point, the look axes rotate and the zoom axis
+/// moves the camera closer or further.
+pub struct OrbitCameraController {
+    pub target: Point3<f32>,
+    pub distance: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    /// Angles of the camera around the target.
+    pub yaw: Rad<f32>,
+    pub pitch: Rad<f32>,
+    /// Radians turned per pixel of mouse motion.
+    pub sensitivity: f32,
+    /// Distance covered per scrolled line.
+    pub zoom_speed: f32,
+    /// Orbits only while this action is held, e.g. bound to a mouse button.
+    /// The camera always orbits if None.
+    pub drag_action: Option<String>,
+    rotate_horizontal: f32,
+    rotate_vertical: f32,
+    scroll: f32,
+}
+
+impl OrbitCameraController {
+    pub fn new<P: Into<Point3<f32>>>(target: P, distance: f32, sensitivity: f32) -> Self {
+        Self {
+            target: target.into(),
+            distance,
+            min_distance: 1.0,
+            max_distance: 100.0,
+            yaw: Rad(0.0),
+            pitch: Rad(0.5),
+            sensitivity,
+            zoom_speed: 1.0,
+            drag_action: None,
+            rotate_horizontal: 0.0,
+            rotate_vertical: 0.0,
+            scroll: 0.0,
+        }
+    }
+
+    pub fn with_drag_action(mut self, action: &str) -> Self {
+        self.drag_action = Some(action.to_string());
+        self
+    }
+}
+
+impl CameraController for OrbitCameraController {
+    /// Reads the "look_x", "look_y" and "zoom" axes.
+    fn process_actions(&mut self, actions: &Actions) {
+        let dragging = match &self.drag_action {
+            Some(action) => actions.is_pressed(action),
+            None => true,
+        };
+        if dragging {
+            self.process_mouse(actions.axis("look_x") as f64, actions.axis("look_y") as f64);
+        }
+        self.process_scroll(actions.axis("zoom") as f64 * input::PIXELS_PER_LINE);
+    }
+
+    fn process_mouse(&mut self, mouse_dx: f64, mouse_dy: f64) {
+        self.rotate_horizontal += mouse_dx as f32;
+        self.rotate_vertical += mouse_dy as f32;
+    }
+
+    fn process_scroll(&mut self, dt: f64) {
+        self.scroll += (dt / input::PIXELS_PER_LINE) as f32;
+    }
+
+    fn update_camera(&mut self, camera: &mut camera::Camera, _dt: time::Duration) {
+        self.yaw += Rad(self.rotate_horizontal) * self.sensitivity;
+        self.pitch += Rad(self.rotate_vertical) * self.sensitivity;
+        self.pitch.0 = self
+            .pitch
+            .0
+            .clamp(-camera::SAFE_FRAC_PI_2, camera::SAFE_FRAC_PI_2);
+        self.distance = (self.distance - self.scroll * self.zoom_speed)
+            .clamp(self.min_distance, self.max_distance);
+
+        let (sin_pitch, cos_pitch) = self.pitch.0.sin_cos();
+        let (sin_yaw, cos_yaw) = self.yaw.0.sin_cos();
+        let offset = Vector3::new(cos_pitch * cos_yaw, sin_pitch, cos_pitch * sin_yaw);
+        camera.position = self.target + offset * self.distance;
+        camera.look_at(self.target);
+
+        self.rotate_horizontal = 0.0;
+        self.rotate_vertical = 0.0;
+        self.scroll = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::Deg;
+
+    // orbits by `steps` mouse deltas per frame, `frames` times, returning the yaw
+    fn orbit(frames: u32, steps: u32, dx: f64) -> f32 {
+        let mut controller = OrbitCameraController::new((0.0, 0.0, 0.0), 10.0, 0.004);
+        let mut camera = camera::Camera::new((0.0, 0.0, 10.0), Deg(0.0), Deg(0.0));
+        let frame_time = time::Duration::from_secs_f32(1.0 / frames as f32);
+        for _ in 0..frames {
+            for _ in 0..steps {
+                controller.process_mouse(dx, 0.0);
+            }
+            controller.update_camera(&mut camera, frame_time);
+        }
+        controller.yaw.0
+    }
+
+    #[test]
+    fn orbit_speed_does_not_depend_on_the_step_rate() {
+        let yaw = orbit(1, 1, 240.0);
+        assert!((yaw - 0.96).abs() < 1e-5, "{}", yaw);
+        assert!((orbit(1, 4, 60.0) - yaw).abs() < 1e-5);
+        assert!((orbit(4, 1, 60.0) - yaw).abs() < 1e-5);
+        assert!((orbit(6, 2, 20.0) - yaw).abs() < 1e-5);
+    }
+}
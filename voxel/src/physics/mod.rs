@@ -1,6 +1,9 @@
+use crate::{actor, delta_time, error, plugin, profiler::SystemsBuilder, state};
+use rapier3d::prelude::*;
+use specs::prelude::*;
 use specs::{Component, DenseVecStorage};
 
-#[derive(Clone, Component, Debug)]
+#[derive(Clone, Component, Debug, Default)]
 pub struct RigidBodyComponent {
     pub rigid_body: rapier3d::dynamics::RigidBodyHandle,
 }
@@ -11,10 +14,168 @@ impl RigidBodyComponent {
     }
 }
 
-impl Default for RigidBodyComponent {
-    fn default() -> Self {
+/// Rapier world, stepped once per simulation step by `PhysicsSys`.
+pub struct Physics {
+    pub pipeline: PhysicsPipeline,
+    pub rigid_body_set: RigidBodySet,
+    pub collider_set: ColliderSet,
+    pub integration_parameters: IntegrationParameters,
+    pub island_manager: IslandManager,
+    pub broad_phase: BroadPhase,
+    pub narrow_phase: NarrowPhase,
+    pub impulse_joint_set: ImpulseJointSet,
+    pub multibody_joint_set: MultibodyJointSet,
+    pub ccd_solver: CCDSolver,
+    /// Updated on every step, used for ray casts.
+    pub query_pipeline: QueryPipeline,
+    pub gravity: Vector<Real>,
+    pub physics_hooks: (),
+    pub event_handler: (),
+}
+
+impl Physics {
+    pub fn new() -> Self {
         Self {
-            rigid_body: Default::default(),
+            pipeline: Default::default(),
+            rigid_body_set: RigidBodySet::new(),
+            collider_set: ColliderSet::new(),
+            gravity: vector![0.0, -9.81, 0.0],
+            integration_parameters: IntegrationParameters::default(),
+            island_manager: IslandManager::new(),
+            broad_phase: BroadPhase::new(),
+            narrow_phase: NarrowPhase::new(),
+            impulse_joint_set: ImpulseJointSet::new(),
+            multibody_joint_set: MultibodyJointSet::new(),
+            ccd_solver: CCDSolver::new(),
+            query_pipeline: QueryPipeline::new(),
+            physics_hooks: (),
+            event_handler: (),
+        }
+    }
+
+    pub fn step(&mut self) {
+        self.pipeline.step(
+            &self.gravity,
+            &self.integration_parameters,
+            &mut self.island_manager,
+            &mut self.broad_phase,
+            &mut self.narrow_phase,
+            &mut self.rigid_body_set,
+            &mut self.collider_set,
+            &mut self.impulse_joint_set,
+            &mut self.multibody_joint_set,
+            &mut self.ccd_solver,
+            Some(&mut self.query_pipeline),
+            &self.physics_hooks,
+            &self.event_handler,
+        );
+    }
+
+    /// Distance along `direction` to the first collider hit within `max_distance`,
+    /// colliders attached to `exclude` are ignored.
+    pub fn cast_ray(
+        &self,
+        origin: cgmath::Point3<f32>,
+        direction: cgmath::Vector3<f32>,
+        max_distance: f32,
+        exclude: Option<RigidBodyHandle>,
+    ) -> Option<f32> {
+        self.cast_ray_collider(origin, direction, max_distance, exclude)
+            .map(|(_, toi)| toi)
+    }
+
+    /// Like `cast_ray`, also returning the collider hit.
+    pub fn cast_ray_collider(
+        &self,
+        origin: cgmath::Point3<f32>,
+        direction: cgmath::Vector3<f32>,
+        max_distance: f32,
+        exclude: Option<RigidBodyHandle>,
+    ) -> Option<(ColliderHandle, f32)> {
+        use cgmath::InnerSpace;
+
+        let direction = direction.normalize();
+        let ray = Ray::new(
+            point![origin.x, origin.y, origin.z],
+            vector![direction.x, direction.y, direction.z],
+        );
+        let mut filter = QueryFilter::default().exclude_sensors();
+        if let Some(body) = exclude {
+            filter = filter.exclude_rigid_body(body);
         }
+
+        self.query_pipeline.cast_ray(
+            &self.rigid_body_set,
+            &self.collider_set,
+            &ray,
+            max_distance,
+            true,
+            filter,
+        )
+    }
+}
+
+impl Default for Physics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Steps the physics world and copies rigid bodies' poses into their actors.
+pub struct PhysicsSys;
+
+impl<'a> System<'a> for PhysicsSys {
+    type SystemData = (
+        Read<'a, delta_time::DeltaTime>,
+        Write<'a, Physics>,
+        WriteStorage<'a, actor::Actor>,
+        ReadStorage<'a, RigidBodyComponent>,
+    );
+
+    fn run(&mut self, (dt, mut physics, mut actors, rbs): Self::SystemData) {
+        log::trace!("running Physics system");
+        // the game clock is paused
+        if dt.dt.is_zero() {
+            return;
+        }
+        physics.integration_parameters.dt = dt.dt.as_secs_f32();
+        physics.step();
+        // update actors' transform
+        for (actor, rb) in (&mut actors, &rbs).join() {
+            let actor_body = match physics.rigid_body_set.get(rb.rigid_body) {
+                Some(body) => body,
+                None => continue,
+            };
+            actor.transform.position.x = actor_body.translation().x;
+            actor.transform.position.y = actor_body.translation().y;
+            actor.transform.position.z = actor_body.translation().z;
+
+            actor.transform.rotation.s = actor_body.rotation().scalar();
+            actor.transform.rotation.v = cgmath::Vector3::from((
+                actor_body.rotation().vector()[0],
+                actor_body.rotation().vector()[1],
+                actor_body.rotation().vector()[2],
+            ));
+        }
+    }
+}
+
+/// Registers rigid bodies, inserts an empty `Physics` world and steps it.
+#[derive(Default)]
+pub struct PhysicsPlugin;
+
+impl plugin::Plugin for PhysicsPlugin {
+    fn setup(&mut self, global_state: &mut state::State) -> error::Result<()> {
+        global_state.world.register::<RigidBodyComponent>();
+        global_state.world.insert(Physics::new());
+
+        Ok(())
+    }
+
+    fn setup_systems<'a, 'b>(
+        &mut self,
+        dispatcher_builder: SystemsBuilder<'a, 'b>,
+    ) -> SystemsBuilder<'a, 'b> {
+        dispatcher_builder.with(PhysicsSys, "physics_sys", &[])
     }
 }
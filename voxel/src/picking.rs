@@ -0,0 +1,202 @@
+use specs::prelude::*;
+
+use crate::{actor, bounds, physics};
+
+/// Nearest object hit by a pick.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hit {
+    /// None for colliders without an entity, e.g. a static ground.
+    pub entity: Option<Entity>,
+    pub point: cgmath::Point3<f32>,
+    pub distance: f32,
+}
+
+/// Objects a ray can pick, see `CameraBundle::cursor_ray` for rays under the cursor.
+///
+/// With physics, entities with a rigid body are picked by their colliders
+/// and every other actor by its mesh triangles.
+pub struct PickWorld<'s, 'a> {
+    pub entities: &'s Entities<'a>,
+    pub actors: &'s ReadStorage<'a, actor::Actor>,
+    pub rigid_bodies: &'s ReadStorage<'a, physics::RigidBodyComponent>,
+    /// None unless the physics plugin is added.
+    pub physics: Option<&'s physics::Physics>,
+}
+
+impl PickWorld<'_, '_> {
+    pub fn pick(&self, ray: &bounds::Ray, max_distance: f32) -> Option<Hit> {
+        let collider = self
+            .physics
+            .and_then(|physics| self.pick_collider(physics, ray, max_distance));
+        let max_distance = collider.map_or(max_distance, |hit| hit.distance);
+        self.pick_mesh(ray, max_distance).or(collider)
+    }
+
+    fn pick_collider(
+        &self,
+        physics: &physics::Physics,
+        ray: &bounds::Ray,
+        max_distance: f32,
+    ) -> Option<Hit> {
+        let (collider, distance) =
+            physics.cast_ray_collider(ray.origin, ray.direction, max_distance, None)?;
+        let body = physics
+            .collider_set
+            .get(collider)
+            .and_then(|collider| collider.parent());
+        let entity = body.and_then(|body| {
+            (self.entities, self.rigid_bodies)
+                .join()
+                .find(|(_, rigid_body)| rigid_body.rigid_body == body)
+                .map(|(entity, _)| entity)
+        });
+
+        Some(Hit {
+            entity,
+            point: ray.at(distance),
+            distance,
+        })
+    }
+
+    fn pick_mesh(&self, ray: &bounds::Ray, max_distance: f32) -> Option<Hit> {
+        let mut nearest: Option<Hit> = None;
+        for (entity, actor) in (self.entities, self.actors).join() {
+            if self.physics.is_some() && self.rigid_bodies.contains(entity) {
+                continue;
+            }
+            let limit = nearest.map_or(max_distance, |hit| hit.distance);
+            let mesh = &actor.model.mesh;
+            let bounds = mesh.bounds.transformed(&actor.transform);
+            match ray.intersect_aabb(&bounds) {
+                Some(distance) if distance <= limit => {}
+                _ => continue,
+            }
+
+            let local = ray.to_local(&actor.transform);
+            let vertex = |i: u32| cgmath::Point3::from(mesh.vertices[i as usize].position);
+            let distance = mesh
+                .indices
+                .chunks_exact(3)
+                .filter_map(|t| local.intersect_triangle(vertex(t[0]), vertex(t[1]), vertex(t[2])))
+                .fold(None, |nearest: Option<f32>, d| {
+                    Some(nearest.map_or(d, |n| n.min(d)))
+                });
+            if let Some(distance) = distance.filter(|distance| *distance <= limit) {
+                nearest = Some(Hit {
+                    entity: Some(entity),
+                    point: ray.at(distance),
+                    distance,
+                });
+            }
+        }
+        nearest
+    }
+}
+
+/// Picks from outside of systems, fetching what `PickWorld` needs from `world`.
+pub fn pick(world: &World, ray: &bounds::Ray, max_distance: f32) -> Option<Hit> {
+    let physics = world.try_fetch::<physics::Physics>();
+
+    PickWorld {
+        entities: &world.entities(),
+        actors: &world.read_storage(),
+        rigid_bodies: &world.read_storage(),
+        physics: physics.as_deref(),
+    }
+    .pick(ray, max_distance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor::{model, transform};
+    use cgmath::{Point3, Vector3};
+    use rapier3d::prelude::{ColliderBuilder, RigidBodyBuilder, Vector};
+
+    // 2x2 quad facing +z
+    fn quad(z: f32) -> actor::Actor {
+        let vertex = |x, y| model::MeshVertex {
+            position: [x, y, 0.0],
+            color: [1.0; 4],
+        };
+        let mesh = model::Mesh::new(
+            "quad".to_string(),
+            vec![0, 1, 2, 0, 2, 3],
+            vec![
+                vertex(-1.0, -1.0),
+                vertex(1.0, -1.0),
+                vertex(1.0, 1.0),
+                vertex(-1.0, 1.0),
+            ],
+        );
+
+        actor::Actor {
+            name: String::new(),
+            transform: transform::Transform {
+                position: Vector3::new(0.0, 0.0, z),
+                rotation: cgmath::Quaternion::new(1.0, 0.0, 0.0, 0.0),
+            },
+            model: model::Model { mesh, color: None },
+        }
+    }
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.register::<actor::Actor>();
+        world.register::<physics::RigidBodyComponent>();
+        world
+    }
+
+    fn forward() -> bounds::Ray {
+        bounds::Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0))
+    }
+
+    #[test]
+    fn picks_the_nearest_mesh() {
+        let mut world = world();
+        world.create_entity().with(quad(-10.0)).build();
+        let near = world.create_entity().with(quad(-5.0)).build();
+
+        let hit = pick(&world, &forward(), 100.0).unwrap();
+        assert_eq!(hit.entity, Some(near));
+        assert_eq!(hit.distance, 5.0);
+        assert_eq!(hit.point, Point3::new(0.0, 0.0, -5.0));
+    }
+
+    #[test]
+    fn misses_meshes_aside_or_too_far() {
+        let mut world = world();
+        world.create_entity().with(quad(-5.0)).build();
+
+        assert!(pick(&world, &forward(), 4.0).is_none());
+        let aside = bounds::Ray::new(Point3::new(3.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0));
+        assert!(pick(&world, &aside, 100.0).is_none());
+    }
+
+    #[test]
+    fn picks_rigid_bodies_by_their_colliders() {
+        let mut world = world();
+        let mut physics = physics::Physics::new();
+        let body = physics.rigid_body_set.insert(
+            RigidBodyBuilder::fixed()
+                .translation(Vector::new(0.0, 0.0, -8.0))
+                .build(),
+        );
+        physics.collider_set.insert_with_parent(
+            ColliderBuilder::cuboid(1.0, 1.0, 1.0).build(),
+            body,
+            &mut physics.rigid_body_set,
+        );
+        physics.step();
+        let entity = world
+            .create_entity()
+            .with(quad(-8.0))
+            .with(physics::RigidBodyComponent::new(body))
+            .build();
+        world.insert(physics);
+
+        let hit = pick(&world, &forward(), 100.0).unwrap();
+        assert_eq!(hit.entity, Some(entity));
+        assert!((hit.distance - 7.0).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,18 @@
+use super::{error, profiler::SystemsBuilder, state};
+
+/// Bundles components, resources and systems that several scenes share.
+///
+/// Plugins are set up once, before the first scene, and their systems are
+/// dispatched ahead of the running scene's systems.
+pub trait Plugin {
+    /// Registers components and inserts resources.
+    fn setup(&mut self, global_state: &mut state::State) -> error::Result<()>;
+
+    /// Adds systems, dependencies may only name systems of this or earlier plugins.
+    fn setup_systems<'a, 'b>(
+        &mut self,
+        dispatcher_builder: SystemsBuilder<'a, 'b>,
+    ) -> SystemsBuilder<'a, 'b> {
+        dispatcher_builder
+    }
+}
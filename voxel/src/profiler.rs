@@ -0,0 +1,405 @@
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, Write};
+use std::sync::Mutex;
+use std::time;
+
+use specs::prelude::*;
+
+#[derive(Clone, Debug)]
+pub struct ProfilerConfig {
+    /// Nothing is recorded while disabled.
+    pub enabled: bool,
+    /// Number of frames kept in the rolling history.
+    pub history: usize,
+    /// Shows the frame summary in the window title.
+    pub overlay: bool,
+    /// Chrome trace written when the app stops running.
+    pub trace_path: Option<std::path::PathBuf>,
+}
+
+impl Default for ProfilerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            history: 120,
+            overlay: false,
+            trace_path: None,
+        }
+    }
+}
+
+/// A timed engine phase or system run, times are relative to the profiler creation.
+#[derive(Clone, Debug)]
+pub struct Span {
+    pub name: String,
+    /// 0 for the main thread, the systems pool threads start at 1.
+    pub thread: usize,
+    pub start: time::Duration,
+    pub duration: time::Duration,
+}
+
+#[derive(Clone, Debug)]
+pub struct Frame {
+    pub index: u64,
+    pub start: time::Duration,
+    pub duration: time::Duration,
+    pub spans: Vec<Span>,
+}
+
+/// Time spent in a span per frame, over the whole history.
+#[derive(Clone, Debug)]
+pub struct SpanStats {
+    pub name: String,
+    pub average: time::Duration,
+    pub max: time::Duration,
+}
+
+/// Records engine phases and systems wrapped in [`Profiled`] frame by frame.
+pub struct Profiler {
+    pub enabled: bool,
+    pub overlay: bool,
+    pub trace_path: Option<std::path::PathBuf>,
+    history: usize,
+    epoch: instant::Instant,
+    frame_start: instant::Instant,
+    frame_index: u64,
+    // spans of the frame in progress, systems record them from the pool threads
+    spans: Mutex<Vec<Span>>,
+    frames: VecDeque<Frame>,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new(&ProfilerConfig::default())
+    }
+}
+
+impl Profiler {
+    pub fn new(config: &ProfilerConfig) -> Self {
+        let now = instant::Instant::now();
+
+        Self {
+            enabled: config.enabled,
+            overlay: config.overlay,
+            trace_path: config.trace_path.clone(),
+            history: config.history.max(1),
+            epoch: now,
+            frame_start: now,
+            frame_index: 0,
+            spans: Mutex::new(Vec::new()),
+            frames: VecDeque::new(),
+        }
+    }
+
+    /// Records a span named `name` from now until the returned guard is dropped.
+    pub fn scope<'a>(&'a self, name: &'a str) -> Scope<'a> {
+        Scope {
+            profiler: self,
+            name,
+            start: instant::Instant::now(),
+        }
+    }
+
+    /// Records a span named `name` from `start` until now.
+    pub fn record(&self, name: &str, start: instant::Instant) {
+        if !self.enabled {
+            return;
+        }
+
+        let span = Span {
+            name: name.to_string(),
+            thread: specs::rayon::current_thread_index().map_or(0, |i| i + 1),
+            start: start.saturating_duration_since(self.epoch),
+            duration: start.elapsed(),
+        };
+        if let Ok(mut spans) = self.spans.lock() {
+            spans.push(span);
+        }
+    }
+
+    /// Closes the frame in progress and moves its spans to the history.
+    pub fn end_frame(&mut self) {
+        let now = instant::Instant::now();
+        let start = std::mem::replace(&mut self.frame_start, now);
+        let spans = match self.spans.get_mut() {
+            Ok(spans) => std::mem::take(spans),
+            Err(_) => Vec::new(),
+        };
+
+        if !self.enabled {
+            return;
+        }
+
+        self.frames.push_back(Frame {
+            index: self.frame_index,
+            start: start.saturating_duration_since(self.epoch),
+            duration: now - start,
+            spans,
+        });
+        self.frame_index += 1;
+        while self.frames.len() > self.history {
+            self.frames.pop_front();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        if let Ok(spans) = self.spans.get_mut() {
+            spans.clear();
+        }
+    }
+
+    /// Recorded frames, oldest first.
+    pub fn frames(&self) -> impl Iterator<Item = &Frame> {
+        self.frames.iter()
+    }
+
+    pub fn last_frame(&self) -> Option<&Frame> {
+        self.frames.back()
+    }
+
+    pub fn average_frame_time(&self) -> time::Duration {
+        if self.frames.is_empty() {
+            return time::Duration::ZERO;
+        }
+
+        let total: time::Duration = self.frames.iter().map(|frame| frame.duration).sum();
+        total / self.frames.len() as u32
+    }
+
+    /// Per span averages over the history, the most expensive first.
+    pub fn summary(&self) -> Vec<SpanStats> {
+        let mut totals: HashMap<&str, (time::Duration, time::Duration)> = HashMap::new();
+        for frame in &self.frames {
+            let mut per_frame: HashMap<&str, time::Duration> = HashMap::new();
+            for span in &frame.spans {
+                *per_frame.entry(&span.name).or_default() += span.duration;
+            }
+            for (name, duration) in per_frame {
+                let (total, max) = totals.entry(name).or_default();
+                *total += duration;
+                *max = (*max).max(duration);
+            }
+        }
+
+        let frames = self.frames.len().max(1) as u32;
+        let mut summary: Vec<SpanStats> = totals
+            .into_iter()
+            .map(|(name, (total, max))| SpanStats {
+                name: name.to_string(),
+                average: total / frames,
+                max,
+            })
+            .collect();
+        summary.sort_by_key(|stats| std::cmp::Reverse(stats.average));
+        summary
+    }
+
+    /// One line summary of the average frame and its most expensive spans.
+    pub fn overlay_text(&self) -> String {
+        let frame_time = self.average_frame_time().as_secs_f64();
+        let fps = if frame_time > 0.0 {
+            1.0 / frame_time
+        } else {
+            0.0
+        };
+
+        let mut text = format!("{:.2} ms ({:.0} fps)", frame_time * 1000.0, fps);
+        for stats in self.summary().iter().take(4) {
+            text.push_str(&format!(
+                " | {} {:.2} ms",
+                stats.name,
+                stats.average.as_secs_f64() * 1000.0
+            ));
+        }
+        text
+    }
+
+    /// Writes the history in the Chrome trace event format,
+    /// it can be opened with chrome://tracing or Perfetto.
+    pub fn write_chrome_trace<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        write!(writer, "{{\"traceEvents\":[")?;
+
+        let mut first = true;
+        for frame in &self.frames {
+            let name = format!("frame {}", frame.index);
+            write_trace_event(
+                &mut writer,
+                &mut first,
+                &name,
+                "frame",
+                0,
+                frame.start,
+                frame.duration,
+            )?;
+            for span in &frame.spans {
+                write_trace_event(
+                    &mut writer,
+                    &mut first,
+                    &span.name,
+                    "span",
+                    span.thread,
+                    span.start,
+                    span.duration,
+                )?;
+            }
+        }
+
+        write!(writer, "],\"displayTimeUnit\":\"ms\"}}")?;
+        writer.flush()
+    }
+
+    pub fn save_chrome_trace<P: AsRef<std::path::Path>>(&self, path: P) -> io::Result<()> {
+        let file = std::fs::File::create(path)?;
+        self.write_chrome_trace(io::BufWriter::new(file))
+    }
+
+    /// Saves the trace to `trace_path` if set, called once the app stops running.
+    pub fn finish(&self) {
+        let path = match (&self.trace_path, self.enabled) {
+            (Some(path), true) => path,
+            _ => return,
+        };
+
+        match self.save_chrome_trace(path) {
+            Ok(_) => log::info!("profiler trace saved to {}", path.display()),
+            Err(err) => log::error!(
+                "failed to save profiler trace to {}: {}",
+                path.display(),
+                err
+            ),
+        }
+    }
+}
+
+fn write_trace_event<W: Write>(
+    writer: &mut W,
+    first: &mut bool,
+    name: &str,
+    category: &str,
+    thread: usize,
+    start: time::Duration,
+    duration: time::Duration,
+) -> io::Result<()> {
+    if !*first {
+        write!(writer, ",")?;
+    }
+    *first = false;
+
+    write!(writer, "{{\"name\":\"")?;
+    for c in name.chars() {
+        match c {
+            '"' => write!(writer, "\\\"")?,
+            '\\' => write!(writer, "\\\\")?,
+            c if c.is_control() => write!(writer, "\\u{:04x}", c as u32)?,
+            c => write!(writer, "{}", c)?,
+        }
+    }
+    write!(
+        writer,
+        "\",\"cat\":\"{}\",\"ph\":\"X\",\"ts\":{:.3},\"dur\":{:.3},\"pid\":1,\"tid\":{}}}",
+        category,
+        start.as_secs_f64() * 1e6,
+        duration.as_secs_f64() * 1e6,
+        thread
+    )
+}
+
+/// Records its span when dropped.
+pub struct Scope<'a> {
+    profiler: &'a Profiler,
+    name: &'a str,
+    start: instant::Instant,
+}
+
+impl Drop for Scope<'_> {
+    fn drop(&mut self) {
+        self.profiler.record(self.name, self.start);
+    }
+}
+
+/// Times every run of the wrapped system under its dispatcher name.
+pub struct Profiled<S> {
+    system: S,
+    name: String,
+}
+
+impl<S> Profiled<S> {
+    pub fn new(system: S, name: &str) -> Self {
+        Self {
+            system,
+            name: name.to_string(),
+        }
+    }
+}
+
+impl<'a, S> System<'a> for Profiled<S>
+where
+    S: System<'a>,
+    S::SystemData: SystemData<'a>,
+{
+    type SystemData = (Read<'a, Profiler>, S::SystemData);
+
+    fn run(&mut self, (profiler, data): Self::SystemData) {
+        let _scope = profiler.scope(&self.name);
+        self.system.run(data);
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        world.entry::<Profiler>().or_insert_with(Default::default);
+        self.system.setup(world);
+    }
+}
+
+/// Dispatcher builder handed to plugins and scenes, every system added to it
+/// is wrapped in [`Profiled`] under its dispatcher name.
+pub struct SystemsBuilder<'a, 'b> {
+    builder: DispatcherBuilder<'a, 'b>,
+}
+
+impl<'a, 'b> SystemsBuilder<'a, 'b> {
+    pub fn new(builder: DispatcherBuilder<'a, 'b>) -> Self {
+        Self { builder }
+    }
+
+    pub fn with<S>(mut self, system: S, name: &str, dep: &[&str]) -> Self
+    where
+        S: for<'c> System<'c> + Send + 'a,
+        for<'c> <S as System<'c>>::SystemData: SystemData<'c>,
+    {
+        self.add(system, name, dep);
+        self
+    }
+
+    pub fn add<S>(&mut self, system: S, name: &str, dep: &[&str])
+    where
+        S: for<'c> System<'c> + Send + 'a,
+        for<'c> <S as System<'c>>::SystemData: SystemData<'c>,
+    {
+        self.builder.add(Profiled::new(system, name), name, dep);
+    }
+
+    /// Runs the system on the main thread once the pool systems are done.
+    pub fn with_thread_local<S>(mut self, system: S, name: &str) -> Self
+    where
+        S: for<'c> System<'c> + 'b,
+        for<'c> <S as System<'c>>::SystemData: SystemData<'c>,
+    {
+        self.builder.add_thread_local(Profiled::new(system, name));
+        self
+    }
+
+    /// Systems added afterwards run once every system added before is done.
+    pub fn with_barrier(mut self) -> Self {
+        self.builder.add_barrier();
+        self
+    }
+
+    pub fn has_system(&self, name: &str) -> bool {
+        self.builder.has_system(name)
+    }
+
+    pub fn build(self) -> Dispatcher<'a, 'b> {
+        self.builder.build()
+    }
+}
@@ -16,7 +16,7 @@ impl CameraBundle {
         let camera_controller = CameraController::new(0.2);
 
         let mut camera_uniform = CameraUniform::new();
-        camera_uniform.update_view_proj(&c);
+        camera_uniform.update_view_proj(c);
 
         let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Camera Buffer"),
@@ -96,6 +96,12 @@ pub struct CameraUniform {
     view_proj: [[f32; 4]; 4],
 }
 
+impl Default for CameraUniform {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl CameraUniform {
     pub fn new() -> Self {
         Self {
@@ -140,29 +146,24 @@ impl CameraController {
     }
 
     pub fn process_events(&mut self, event: &WinEvent) -> bool {
-        match event {
-            WinEvent::Space => {
-                self.is_up_pressed = true;
-                true
-            }
-            WinEvent::Up => {
-                self.is_forward_pressed = true;
-                true
-            }
-            WinEvent::Left => {
-                self.is_left_pressed = true;
-                true
-            }
-            WinEvent::Down => {
-                self.is_backward_pressed = true;
-                true
-            }
-            WinEvent::Right => {
-                self.is_right_pressed = true;
-                true
-            }
-            _ => false,
+        let (key, pressed) = match event {
+            WinEvent::Key {
+                key: Some(key),
+                state,
+                ..
+            } => (key, *state == ButtonState::Pressed),
+            _ => return false,
+        };
+
+        match key {
+            Key::Space => self.is_up_pressed = pressed,
+            Key::W | Key::Up => self.is_forward_pressed = pressed,
+            Key::A | Key::Left => self.is_left_pressed = pressed,
+            Key::S | Key::Down => self.is_backward_pressed = pressed,
+            Key::D | Key::Right => self.is_right_pressed = pressed,
+            _ => return false,
         }
+        true
     }
 
     pub fn update_camera(&mut self, camera: &mut Camera) {
@@ -1,100 +1,168 @@
-use crate::actor::{self, model};
-use wgpu::util::DeviceExt;
 use super::transform;
+use crate::actor::model;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use wgpu::util::DeviceExt;
 
-pub struct BuffActor<'a> {
-    pub actor: &'a actor::Actor,
-    pub buffers: Buffers,
+/// GPU buffers of a mesh, the vertices and indices are uploaded once
+/// and the instances rewritten every frame.
+pub struct MeshBuffers {
+    pub vertex_buffer: wgpu::Buffer,
+    pub index_buffer: wgpu::Buffer,
+    pub instance_buffer: wgpu::Buffer,
+    pub num_indices: u32,
+    /// Instances of each view in the instance buffer, as last written.
+    pub ranges: Vec<Range<u32>>,
+    // instances the instance buffer can hold
+    capacity: usize,
 }
 
-impl<'a> BuffActor<'a> {
-    pub fn new(device: &wgpu::Device, actor: &'a actor::Actor) -> Self {
+impl MeshBuffers {
+    pub fn new(device: &wgpu::Device, mesh: &model::Mesh) -> Self {
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{:?} Vertex Buffer", mesh.id)),
+            contents: bytemuck::cast_slice(&mesh.vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{:?} Index Buffer", mesh.id)),
+            contents: bytemuck::cast_slice(&mesh.indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        let capacity = 16;
         Self {
-            actor: actor,
-            buffers: Buffers::new(
-                actor.model.mesh.id.clone(),
-                device,
-                &actor.model.mesh.vertices,
-                &actor.model.mesh.indices,
-                &actor.transform.into(),
-            ),
+            vertex_buffer,
+            index_buffer,
+            instance_buffer: create_instance_buffer(device, &mesh.id, capacity),
+            num_indices: mesh.indices.len() as u32,
+            ranges: Vec::new(),
+            capacity,
         }
     }
 
-    pub fn update(&mut self, device: &wgpu::Device) {
-        self.buffers = Buffers::new(
-            self.actor.model.mesh.id.clone(),
-            device,
-            &self.actor.model.mesh.vertices,
-            &self.actor.model.mesh.indices,
-            &self.actor.transform.into(),
-        );
+    /// Writes the instances of every view, in the views' order,
+    /// growing the instance buffer when they don't fit.
+    pub fn write_instances(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        id: &str,
+        instances: &[Vec<transform::Instance>],
+    ) {
+        self.ranges.clear();
+        let mut start = 0;
+        for view_instances in instances {
+            let end = start + view_instances.len() as u32;
+            self.ranges.push(start..end);
+            start = end;
+        }
+
+        let count = start as usize;
+        if count > self.capacity {
+            self.capacity = count.next_power_of_two();
+            self.instance_buffer = create_instance_buffer(device, id, self.capacity);
+        }
+        for (view_instances, range) in instances.iter().zip(&self.ranges) {
+            if !view_instances.is_empty() {
+                let offset = range.start as usize * std::mem::size_of::<transform::Instance>();
+                queue.write_buffer(
+                    &self.instance_buffer,
+                    offset as wgpu::BufferAddress,
+                    bytemuck::cast_slice(view_instances),
+                );
+            }
+        }
     }
 }
 
-pub trait DrawModel<'a> {
-    fn draw_model(&mut self, buff_actor: &'a BuffActor, camera_bind_group: &'a wgpu::BindGroup);
-    fn draw_mesh(&mut self, buff_actor: &'a BuffActor, camera_bind_group: &'a wgpu::BindGroup);
+fn create_instance_buffer(device: &wgpu::Device, id: &str, capacity: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some(&format!("{:?} Instance Buffer", id)),
+        size: (capacity * std::mem::size_of::<transform::Instance>()) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
 }
 
-impl<'a, 'b> DrawModel<'b> for wgpu::RenderPass<'a>
-where
-    'b: 'a,
-{
-    fn draw_model(&mut self, buff_actor: &'b BuffActor, camera_bind_group: &'b wgpu::BindGroup) {
-        self.draw_mesh(&buff_actor, camera_bind_group);
+/// `MeshBuffers` by mesh id, kept while an actor uses the mesh.
+/// Meshes sharing an id are expected to share their vertices and indices.
+#[derive(Default)]
+pub struct MeshCache {
+    meshes: HashMap<String, MeshBuffers>,
+}
+
+impl MeshCache {
+    /// Uploads the mesh the first time it's seen.
+    pub fn get_or_upload(&mut self, device: &wgpu::Device, mesh: &model::Mesh) -> &mut MeshBuffers {
+        self.meshes.entry(mesh.id.clone()).or_insert_with(|| {
+            log::debug!("uploading mesh {:?}", mesh.id);
+            MeshBuffers::new(device, mesh)
+        })
     }
 
-    fn draw_mesh(&mut self, buff_actor: &'b BuffActor, camera_bind_group: &'b wgpu::BindGroup) {
-        self.set_vertex_buffer(0, buff_actor.buffers.vertex_buffer.slice(..));
-        self.set_vertex_buffer(1, buff_actor.buffers.transform_buffer.slice(..));
-        self.set_index_buffer(
-            buff_actor.buffers.index_buffer.slice(..),
-            wgpu::IndexFormat::Uint32,
-        );
-        self.set_bind_group(0, camera_bind_group, &[]);
-        self.draw_indexed(0..buff_actor.actor.model.mesh.indices.len() as u32, 0, 0..1);
+    pub fn get(&self, id: &str) -> Option<&MeshBuffers> {
+        self.meshes.get(id)
     }
-}
 
-pub struct Buffers {
-    pub vertex_buffer: wgpu::Buffer,
-    pub index_buffer: wgpu::Buffer,
-    pub transform_buffer: wgpu::Buffer,
-}
+    /// Frees the buffers of every mesh not in `used`.
+    pub fn retain(&mut self, used: &HashSet<&str>) {
+        self.meshes.retain(|id, _| used.contains(id.as_str()));
+    }
 
-impl Buffers {
-    pub fn new(
-        id: String,
-        device: &wgpu::Device,
-        vertices: &Vec<model::MeshVertex>,
-        indices: &Vec<u32>,
-        transf: &transform::TransformMatrix,
-    ) -> Self {
-        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some(&format!("{:?} Vertex Buffer", id)),
-            contents: bytemuck::cast_slice(vertices),
-            usage: wgpu::BufferUsages::VERTEX,
-        });
+    pub fn len(&self) -> usize {
+        self.meshes.len()
+    }
 
-        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some(&format!("{:?} Index Buffer", id)),
-            contents: bytemuck::cast_slice(indices),
-            usage: wgpu::BufferUsages::INDEX,
-        });
+    pub fn is_empty(&self) -> bool {
+        self.meshes.is_empty()
+    }
+}
 
-        let transform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some(&format!("{:?} Transform Buffer", id)),
-            contents: bytemuck::bytes_of(transf),
-            usage: wgpu::BufferUsages::VERTEX,
-        });
+pub trait DrawModel<'a> {
+    /// Draws the instances `view` wrote to the mesh's instance buffer.
+    fn draw_mesh_view(
+        &mut self,
+        buffers: &'a MeshBuffers,
+        view: usize,
+        camera_bind_group: &'a wgpu::BindGroup,
+    );
+    fn draw_mesh_instanced(
+        &mut self,
+        buffers: &'a MeshBuffers,
+        instances: Range<u32>,
+        camera_bind_group: &'a wgpu::BindGroup,
+    );
+}
 
-        Self {
-            vertex_buffer,
-            index_buffer,
-            transform_buffer,
+impl<'a, 'b> DrawModel<'b> for wgpu::RenderPass<'a>
+where
+    'b: 'a,
+{
+    fn draw_mesh_view(
+        &mut self,
+        buffers: &'b MeshBuffers,
+        view: usize,
+        camera_bind_group: &'b wgpu::BindGroup,
+    ) {
+        if let Some(instances) = buffers.ranges.get(view).filter(|range| !range.is_empty()) {
+            self.draw_mesh_instanced(buffers, instances.clone(), camera_bind_group);
         }
     }
+
+    fn draw_mesh_instanced(
+        &mut self,
+        buffers: &'b MeshBuffers,
+        instances: Range<u32>,
+        camera_bind_group: &'b wgpu::BindGroup,
+    ) {
+        self.set_vertex_buffer(0, buffers.vertex_buffer.slice(..));
+        self.set_vertex_buffer(1, buffers.instance_buffer.slice(..));
+        self.set_index_buffer(buffers.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        self.set_bind_group(0, camera_bind_group, &[]);
+        self.draw_indexed(0..buffers.num_indices, 0, instances);
+    }
 }
 
 pub trait Vertex {
@@ -120,30 +188,6 @@ impl Vertex for model::MeshVertex {
                     shader_location: 1,
                     format: wgpu::VertexFormat::Float32x4,
                 },
-                // wgpu::VertexAttribute {
-                //     // transform 1
-                //     offset: mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
-                //     shader_location: 2,
-                //     format: wgpu::VertexFormat::Float32x4,
-                // },
-                // wgpu::VertexAttribute {
-                //     // transform 2
-                //     offset: mem::size_of::<[f32; 13]>() as wgpu::BufferAddress,
-                //     shader_location: 3,
-                //     format: wgpu::VertexFormat::Float32x4,
-                // },
-                // wgpu::VertexAttribute {
-                //     // transform 3
-                //     offset: mem::size_of::<[f32; 18]>() as wgpu::BufferAddress,
-                //     shader_location: 4,
-                //     format: wgpu::VertexFormat::Float32x4,
-                // },
-                // wgpu::VertexAttribute {
-                //     // transform 4
-                //     offset: mem::size_of::<[f32; 23]>() as wgpu::BufferAddress,
-                //     shader_location: 5,
-                //     format: wgpu::VertexFormat::Float32x4,
-                // },
             ],
         }
     }
@@ -1,43 +1,46 @@
 use super::transform;
 use super::{model::Vertex, texture};
 use crate::actor::{resources, model as actormodel};
+use crate::error;
 
 pub fn create_pipelines(
     device: &wgpu::Device,
     config: &wgpu::SurfaceConfiguration,
     camera_bind_group_layout: &wgpu::BindGroupLayout,
-) -> (wgpu::RenderPipeline,) {
+) -> error::Result<(wgpu::RenderPipeline,)> {
     let render_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
         label: Some("Render Pipeline Layout"),
-        bind_group_layouts: &[&camera_bind_group_layout],
+        bind_group_layouts: &[camera_bind_group_layout],
         push_constant_ranges: &[],
     });
 
-    let render_uniform_color_pipeline =
-        build_uniform_color_pipeline(&device, &config, &render_pipeline_layout);
+    let render_instance_pipeline =
+        build_instance_pipeline(device, config, &render_pipeline_layout)?;
 
-    (render_uniform_color_pipeline,)
+    Ok((render_instance_pipeline,))
 }
 
-fn build_uniform_color_pipeline(
+fn build_instance_pipeline(
     device: &wgpu::Device,
     config: &wgpu::SurfaceConfiguration,
     render_pipeline_layout: &wgpu::PipelineLayout,
-) -> wgpu::RenderPipeline {
+) -> error::Result<wgpu::RenderPipeline> {
     log::debug!("Shader");
-    let shader_str = resources::load_string("uniform_color_shader.wgsl").unwrap();
+    let shader_path = "instance_shader.wgsl";
+    let shader_str =
+        resources::load_string(shader_path).map_err(|err| error::Error::asset(shader_path, err))?;
     let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-        label: Some("uniform_color_shader.wgsl"),
+        label: Some(shader_path),
         source: wgpu::ShaderSource::Wgsl(shader_str.into()),
     });
 
-    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+    Ok(device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
         label: Some("Render Pipeline"),
-        layout: Some(&render_pipeline_layout),
+        layout: Some(render_pipeline_layout),
         vertex: wgpu::VertexState {
             module: &shader,
             entry_point: "vs_main",
-            buffers: &[actormodel::MeshVertex::desc(), transform::TransformMatrix::desc()],
+            buffers: &[actormodel::MeshVertex::desc(), transform::Instance::desc()],
         },
         fragment: Some(wgpu::FragmentState {
             module: &shader,
@@ -79,5 +82,5 @@ fn build_uniform_color_pipeline(
         // If the pipeline will be used with a multiview render pass, this
         // indicates how many array layers the attachments will have.
         multiview: None,
-    })
+    }))
 }
@@ -1,8 +1,10 @@
 use super::model::{self, DrawModel};
 use super::pipeline;
 use super::texture;
-use crate::actor;
+use super::transform;
+use crate::{actor, bounds, camera, error, profiler};
 use futures::executor;
+use std::collections::{HashMap, HashSet};
 use std::iter;
 use wgpu;
 use winit::{self, window::Window};
@@ -15,16 +17,107 @@ pub struct Render {
     pub size: (u32, u32),
     pub render_pipeline: wgpu::RenderPipeline,
     pub depth_texture: texture::Texture,
+    pub clear_color: wgpu::Color,
+    /// Skips actors outside of every view.
+    pub culling: bool,
+    // shared by the pipeline and every camera's bind group
+    camera_bind_group_layout: wgpu::BindGroupLayout,
+    targets: HashMap<String, RenderTexture>,
+    meshes: model::MeshCache,
+    stats: RenderStats,
 }
 
-impl Default for Render {
+/// Actor counts of the last drawn frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RenderStats {
+    pub actors: usize,
+    /// Actors in at least one view.
+    pub visible: usize,
+    pub culled: usize,
+    /// Instanced draw calls summed over the views.
+    pub draws: usize,
+    /// Actors drawn summed over the views.
+    pub instances: usize,
+    /// Meshes with buffers on the GPU.
+    pub meshes: usize,
+}
+
+/// Offscreen color and depth textures cameras can be drawn to.
+pub struct RenderTexture {
+    pub size: (u32, u32),
+    color: texture::Texture,
+    depth: texture::Texture,
+}
+
+impl RenderTexture {
+    pub fn texture(&self) -> &wgpu::Texture {
+        &self.color.texture
+    }
+
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.color.view
+    }
+
+    pub fn sampler(&self) -> &wgpu::Sampler {
+        &self.color.sampler
+    }
+}
+
+/// An actor to draw, with its transform as of this frame.
+#[derive(Clone, Copy, Debug)]
+pub struct ActorInstance<'a> {
+    pub mesh: &'a actor::model::Mesh,
+    pub transform: actor::transform::Transform,
+    pub color: Option<[f32; 4]>,
+}
+
+/// Uniform buffer and bind group of a camera, created once and rewritten every frame.
+pub struct CameraBinding {
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+impl CameraBinding {
+    pub fn bind_group(&self) -> &wgpu::BindGroup {
+        &self.bind_group
+    }
+}
+
+/// A camera to draw, its bind group holds the view projection.
+pub struct View<'a> {
+    pub camera_bg: &'a wgpu::BindGroup,
+    pub frustum: bounds::Frustum,
+    pub viewport: camera::Viewport,
+    pub target: &'a camera::RenderTarget,
+}
+
+#[derive(Clone, Debug)]
+pub struct RenderConfig {
+    pub present_mode: wgpu::PresentMode,
+    /// Preferred surface format, the first supported one is used when it's None or unsupported.
+    pub surface_format: Option<wgpu::TextureFormat>,
+    pub clear_color: wgpu::Color,
+    pub culling: bool,
+}
+
+impl Default for RenderConfig {
     fn default() -> Self {
-        todo!()
+        Self {
+            present_mode: wgpu::PresentMode::Fifo,
+            surface_format: None,
+            clear_color: wgpu::Color {
+                r: 1.0,
+                g: (248.0_f64 / 255.0).powf(2.2),
+                b: (234.0_f64 / 255.0).powf(2.2),
+                a: 1.0,
+            },
+            culling: true,
+        }
     }
 }
 
 impl Render {
-    pub fn new(window: &Window) -> Self {
+    pub fn new(window: &Window, render_config: &RenderConfig) -> error::Result<Self> {
         let size = window.inner_size();
 
         // The instance is a handle to our GPU
@@ -37,7 +130,7 @@ impl Render {
             compatible_surface: Some(&surface),
             force_fallback_adapter: false,
         }))
-        .unwrap();
+        .ok_or(error::Error::AdapterNotFound)?;
 
         log::debug!("device and queue");
         let (device, queue) = executor::block_on(adapter.request_device(
@@ -48,16 +141,30 @@ impl Render {
             },
             // Some(&std::path::Path::new("trace")), // Trace path
             None, // Trace path
-        ))
-        .unwrap();
+        ))?;
 
         log::debug!("Surface");
+        let supported_formats = surface.get_supported_formats(&adapter);
+        let default_format = *supported_formats
+            .first()
+            .ok_or(error::Error::UnsupportedSurface)?;
+        let format = match render_config.surface_format {
+            Some(format) if supported_formats.contains(&format) => format,
+            Some(format) => {
+                log::warn!(
+                    "surface format {:?} not supported, using the default",
+                    format
+                );
+                default_format
+            }
+            None => default_format,
+        };
         let config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: surface.get_supported_formats(&adapter)[0],
+            format,
             width: size.width,
             height: size.height,
-            present_mode: wgpu::PresentMode::Fifo,
+            present_mode: render_config.present_mode,
         };
 
         surface.configure(&device, &config);
@@ -84,16 +191,82 @@ impl Render {
 
         log::debug!("Pipelines");
         let (render_pipeline,) =
-            pipeline::create_pipelines(&device, &config, &camera_bind_group_layout);
+            pipeline::create_pipelines(&device, &config, &camera_bind_group_layout)?;
 
-        Self {
+        Ok(Self {
             surface,
             device,
             queue,
             config,
             size: (size.width, size.height),
-            render_pipeline: render_pipeline,
+            render_pipeline,
             depth_texture,
+            clear_color: render_config.clear_color,
+            culling: render_config.culling,
+            camera_bind_group_layout,
+            targets: HashMap::new(),
+            meshes: model::MeshCache::default(),
+            stats: RenderStats::default(),
+        })
+    }
+
+    /// Creates or replaces the offscreen target `name`, cameras with
+    /// `RenderTarget::Texture(name)` are drawn to it.
+    pub fn create_render_target(&mut self, name: &str, (width, height): (u32, u32)) {
+        let config = wgpu::SurfaceConfiguration {
+            width: width.max(1),
+            height: height.max(1),
+            ..self.config.clone()
+        };
+        let target = RenderTexture {
+            size: (config.width, config.height),
+            color: texture::Texture::create_render_target(&self.device, &config, name),
+            depth: texture::Texture::create_depth_texture(&self.device, &config, name),
+        };
+        self.targets.insert(name.to_string(), target);
+    }
+
+    pub fn remove_render_target(&mut self, name: &str) -> Option<RenderTexture> {
+        self.targets.remove(name)
+    }
+
+    pub fn render_target(&self, name: &str) -> Option<&RenderTexture> {
+        self.targets.get(name)
+    }
+
+    pub fn create_camera_binding(&self) -> CameraBinding {
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Camera Buffer"),
+            size: std::mem::size_of::<camera::CameraUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.camera_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+            label: Some("camera_bind_group"),
+        });
+
+        CameraBinding { buffer, bind_group }
+    }
+
+    pub fn write_camera(&self, binding: &CameraBinding, uniform: &camera::CameraUniform) {
+        self.queue
+            .write_buffer(&binding.buffer, 0, bytemuck::cast_slice(&[*uniform]));
+    }
+
+    pub fn stats(&self) -> RenderStats {
+        self.stats
+    }
+
+    /// Size in pixels of a target, None for unknown textures.
+    pub fn target_size(&self, target: &camera::RenderTarget) -> Option<(u32, u32)> {
+        match target {
+            camera::RenderTarget::Surface => Some(self.size),
+            camera::RenderTarget::Texture(name) => self.targets.get(name).map(|t| t.size),
         }
     }
 
@@ -108,13 +281,20 @@ impl Render {
         }
     }
 
+    /// Draws the actors once per view, in order. Each target is cleared
+    /// before the first view drawn to it, the surface is cleared even without views.
+    /// Actors whose mesh bounds are outside of a view's frustum are skipped, the
+    /// others are drawn with one instanced call per mesh.
     pub fn draw(
         &mut self,
-        actors: &Vec<actor::Actor>,
-        camera_bg: &wgpu::BindGroup,
+        actors: &[ActorInstance],
+        views: &[View],
+        profiler: &profiler::Profiler,
     ) -> Result<(), wgpu::SurfaceError> {
+        let start = instant::Instant::now();
         let output = self.surface.get_current_texture()?;
-        let view = output
+        profiler.record("render::acquire", start);
+        let surface_view = output
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
 
@@ -124,46 +304,134 @@ impl Render {
                 label: Some("Render Encoder"),
             });
 
-        let buff_actors: Vec<model::BuffActor> = actors
+        let start = instant::Instant::now();
+        // actors sharing a mesh are batched, with the instances of each view
+        let mut batch_indices: HashMap<&str, usize> = HashMap::new();
+        let mut meshes: Vec<&actor::model::Mesh> = Vec::new();
+        let mut instances: Vec<Vec<Vec<transform::Instance>>> = Vec::new();
+        let mut visible = 0;
+        for actor in actors {
+            let mesh = actor.mesh;
+            let bounds = mesh.bounds.transformed(&actor.transform);
+            let instance = transform::Instance::new(actor.transform, actor.color);
+            let mut is_visible = false;
+            for (v, view) in views.iter().enumerate() {
+                if self.culling && !view.frustum.intersects_aabb(&bounds) {
+                    continue;
+                }
+                let batch = *batch_indices.entry(&mesh.id).or_insert_with(|| {
+                    meshes.push(mesh);
+                    instances.push(vec![Vec::new(); views.len()]);
+                    meshes.len() - 1
+                });
+                instances[batch][v].push(instance);
+                is_visible = true;
+            }
+            visible += is_visible as usize;
+        }
+        profiler.record("render::culling", start);
+
+        let start = instant::Instant::now();
+        let used: HashSet<&str> = actors.iter().map(|actor| actor.mesh.id.as_str()).collect();
+        self.meshes.retain(&used);
+        for (mesh, instances) in meshes.iter().zip(&instances) {
+            self.meshes
+                .get_or_upload(&self.device, mesh)
+                .write_instances(&self.device, &self.queue, &mesh.id, instances);
+        }
+        let batches: Vec<&model::MeshBuffers> = meshes
             .iter()
-            .map(|actor| model::BuffActor::new(&self.device, actor))
+            .filter_map(|mesh| self.meshes.get(&mesh.id))
             .collect();
+        profiler.record("render::buffers", start);
 
-        {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: (255.0 / 255.0 as f64).powf(2.2),
-                            g: (248.0 / 255.0 as f64).powf(2.2),
-                            b: (234.0 / 255.0 as f64).powf(2.2),
-                            a: 1.0,
-                        }),
-                        store: true,
-                    },
-                })],
-                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                    view: &self.depth_texture.view,
-                    depth_ops: Some(wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(1.0),
-                        store: true,
-                    }),
-                    stencil_ops: None,
-                }),
-            });
+        let start = instant::Instant::now();
+        let surface = camera::RenderTarget::Surface;
+        let mut cleared = HashSet::new();
+        let (mut draws, mut drawn_instances) = (0, 0);
+        for (v, view) in views.iter().enumerate() {
+            let (color, depth, size) = match view.target {
+                camera::RenderTarget::Surface => {
+                    (&surface_view, &self.depth_texture.view, self.size)
+                }
+                camera::RenderTarget::Texture(name) => match self.targets.get(name) {
+                    Some(target) => (&target.color.view, &target.depth.view, target.size),
+                    None => {
+                        log::warn!("render target {:?} not found", name);
+                        continue;
+                    }
+                },
+            };
+            let (x, y, width, height) = view.viewport.rect(size);
+            let load = if cleared.insert(view.target) {
+                wgpu::LoadOp::Clear(self.clear_color)
+            } else {
+                wgpu::LoadOp::Load
+            };
 
-            for buff_actor in &buff_actors {
-                render_pass.set_pipeline(&self.render_pipeline);
-                render_pass.draw_model(&buff_actor, camera_bg);
+            let mut render_pass = begin_pass(&mut encoder, color, depth, load);
+            if width < 1.0 || height < 1.0 {
+                continue;
+            }
+            render_pass.set_viewport(x, y, width, height, 0.0, 1.0);
+            render_pass.set_pipeline(&self.render_pipeline);
+            for buffers in &batches {
+                let count = buffers.ranges[v].len();
+                if count > 0 {
+                    render_pass.draw_mesh_view(buffers, v, view.camera_bg);
+                    draws += 1;
+                    drawn_instances += count;
+                }
             }
         }
+        if !cleared.contains(&surface) {
+            begin_pass(
+                &mut encoder,
+                &surface_view,
+                &self.depth_texture.view,
+                wgpu::LoadOp::Clear(self.clear_color),
+            );
+        }
 
+        profiler.record("render::encode", start);
+        self.stats = RenderStats {
+            actors: actors.len(),
+            visible,
+            culled: actors.len() - visible,
+            draws,
+            instances: drawn_instances,
+            meshes: self.meshes.len(),
+        };
+
+        let start = instant::Instant::now();
         self.queue.submit(iter::once(encoder.finish()));
         output.present();
+        profiler.record("render::submit", start);
 
         Ok(())
     }
 }
+
+fn begin_pass<'a>(
+    encoder: &'a mut wgpu::CommandEncoder,
+    color: &'a wgpu::TextureView,
+    depth: &'a wgpu::TextureView,
+    load: wgpu::LoadOp<wgpu::Color>,
+) -> wgpu::RenderPass<'a> {
+    encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("Render Pass"),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: color,
+            resolve_target: None,
+            ops: wgpu::Operations { load, store: true },
+        })],
+        depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+            view: depth,
+            depth_ops: Some(wgpu::Operations {
+                load: wgpu::LoadOp::Clear(1.0),
+                store: true,
+            }),
+            stencil_ops: None,
+        }),
+    })
+}
@@ -1,4 +1,4 @@
-use crate::actor::transform;
+use crate::actor::{self, transform};
 
 #[repr(C)]
 #[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
@@ -6,11 +6,37 @@ pub struct TransformMatrix {
     pub model: [[f32; 4]; 4],
 }
 
-impl TransformMatrix {
+impl From<transform::Transform> for TransformMatrix {
+    fn from(t: transform::Transform) -> Self {
+        Self {
+            model: (cgmath::Matrix4::from_translation(t.position)
+                * cgmath::Matrix4::from(t.rotation))
+            .into(),
+        }
+    }
+}
+
+/// Per instance data of an instanced draw.
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Instance {
+    pub transform: TransformMatrix,
+    pub color: [f32; 4],
+}
+
+impl Instance {
+    pub fn new(transform: transform::Transform, color: Option<[f32; 4]>) -> Self {
+        Self {
+            transform: transform.into(),
+            // the mesh vertices of every actor are colored the same way
+            color: color.unwrap_or_default(),
+        }
+    }
+
     pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
         use std::mem;
         wgpu::VertexBufferLayout {
-            array_stride: mem::size_of::<TransformMatrix>() as wgpu::BufferAddress,
+            array_stride: mem::size_of::<Instance>() as wgpu::BufferAddress,
             step_mode: wgpu::VertexStepMode::Instance,
             attributes: &[
                 wgpu::VertexAttribute {
@@ -37,17 +63,19 @@ impl TransformMatrix {
                     shader_location: 5,
                     format: wgpu::VertexFormat::Float32x4,
                 },
+                wgpu::VertexAttribute {
+                    // color
+                    offset: mem::size_of::<[f32; 16]>() as wgpu::BufferAddress,
+                    shader_location: 6,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
             ],
         }
     }
 }
 
-impl From<transform::Transform> for TransformMatrix {
-    fn from(t: transform::Transform) -> Self {
-        Self {
-            model: (cgmath::Matrix4::from_translation(t.position)
-                * cgmath::Matrix4::from(t.rotation))
-            .into(),
-        }
+impl From<&actor::Actor> for Instance {
+    fn from(actor: &actor::Actor) -> Self {
+        Self::new(actor.transform, actor.model.color)
     }
 }
@@ -1,81 +1,161 @@
+use crate::cursor::{Cursor, CursorGrab};
+use crate::error;
 use crate::event::*;
 use crate::game_ticker;
+use crate::profiler;
 use crate::state;
 use crate::ticker::Ticker;
+use specs::WorldExt;
 use winit::{
     event::*,
     event_loop::{ControlFlow, EventLoop},
-    window::WindowBuilder,
+    platform::run_return::EventLoopExtRunReturn,
+    window::{Fullscreen, WindowBuilder},
 };
 
-pub fn create_win() -> (winit::event_loop::EventLoop<()>, winit::window::Window) {
+#[derive(Clone, Debug)]
+pub struct WindowConfig {
+    pub title: String,
+    pub size: (u32, u32),
+    pub fullscreen: bool,
+    pub resizable: bool,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            title: String::from("voxel"),
+            size: (800, 600),
+            fullscreen: false,
+            resizable: true,
+        }
+    }
+}
+
+pub fn create_win(
+    config: &WindowConfig,
+) -> error::Result<(winit::event_loop::EventLoop<()>, winit::window::Window)> {
     let ev_loop = EventLoop::new();
-    let window = WindowBuilder::new().build(&ev_loop).unwrap();
+    let mut builder = WindowBuilder::new()
+        .with_title(config.title.clone())
+        .with_inner_size(winit::dpi::PhysicalSize::new(config.size.0, config.size.1))
+        .with_resizable(config.resizable);
+    if config.fullscreen {
+        // borderless on the current monitor
+        builder = builder.with_fullscreen(Some(Fullscreen::Borderless(None)));
+    }
+    let window = builder.build(&ev_loop)?;
+
+    Ok((ev_loop, window))
+}
+
+/// Translates the window events the engine forwards to `WinEvents`.
+fn map_window_event(event: &WindowEvent, modifiers: Modifiers) -> Option<WinEvent> {
+    let win_event = match event {
+        WindowEvent::KeyboardInput { input, .. } => WinEvent::Key {
+            key: input.virtual_keycode,
+            scancode: input.scancode,
+            state: input.state.into(),
+            modifiers,
+        },
+        WindowEvent::MouseInput { button, state, .. } => WinEvent::MouseButton {
+            button: *button,
+            state: (*state).into(),
+            modifiers,
+        },
+        WindowEvent::MouseWheel { delta, .. } => match delta {
+            MouseScrollDelta::LineDelta(_, y) => WinEvent::Scroll(MouseScroll::Line(*y as f64)),
+            MouseScrollDelta::PixelDelta(position) => {
+                WinEvent::Scroll(MouseScroll::Pixel(position.y))
+            }
+        },
+        WindowEvent::CursorMoved { position, .. } => WinEvent::CursorMoved(position.x, position.y),
+        WindowEvent::CursorEntered { .. } => WinEvent::CursorEntered,
+        WindowEvent::CursorLeft { .. } => WinEvent::CursorLeft,
+        WindowEvent::ModifiersChanged(state) => WinEvent::ModifiersChanged(*state),
+        WindowEvent::Focused(focused) => WinEvent::Focused(*focused),
+        WindowEvent::CloseRequested => WinEvent::Close,
+        WindowEvent::Resized(size) => WinEvent::Resize(size.width, size.height),
+        WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+            WinEvent::Resize(new_inner_size.width, new_inner_size.height)
+        }
+        _ => return None,
+    };
+
+    Some(win_event)
+}
+
+fn with_cursor<R>(global_state: &state::State, f: impl FnOnce(&mut Cursor) -> R) -> Option<R> {
+    global_state
+        .world
+        .try_fetch_mut::<Cursor>()
+        .map(|mut cursor| f(&mut cursor))
+}
+
+fn apply_cursor(window: &winit::window::Window, (grab, visible): (CursorGrab, bool)) {
+    log::debug!("setting cursor grab to {:?}, visible {}", grab, visible);
+    if let Err(err) = window.set_cursor_grab(grab != CursorGrab::None) {
+        log::warn!("failed to set cursor grab: {}", err);
+    }
+    window.set_cursor_visible(visible);
+    if grab == CursorGrab::Locked {
+        center_cursor(window);
+    }
+}
 
-    (ev_loop, window)
+// grabbing only confines the cursor on some platforms
+fn center_cursor(window: &winit::window::Window) {
+    let size = window.inner_size();
+    let center = winit::dpi::PhysicalPosition::new(size.width / 2, size.height / 2);
+    if let Err(err) = window.set_cursor_position(center) {
+        log::warn!("failed to center cursor: {}", err);
+    }
 }
 
+/// Drives the game ticker from window events until the window is closed,
+/// every scene is popped or a tick fails, whose error is then returned.
 pub fn run(
-    ev_loop: winit::event_loop::EventLoop<()>,
+    mut ev_loop: winit::event_loop::EventLoop<()>,
     window: winit::window::Window,
+    title: &str,
     mut game_ticker: game_ticker::GameTicker,
     mut global_state: state::State,
-) {
-    ev_loop.run(move |event, _, control_flow| {
+) -> error::Result<()> {
+    let mut result = Ok(());
+    let mut last_overlay = instant::Instant::now();
+    let mut modifiers = Modifiers::empty();
+    let mut applied_cursor = (CursorGrab::None, true);
+
+    ev_loop.run_return(|event, _, control_flow| {
         log::trace!("running event loop");
+        if result.is_err() || matches!(event, Event::LoopDestroyed) {
+            return;
+        }
         *control_flow = ControlFlow::Poll;
         let mut win_events = Vec::<WinEvent>::new();
 
         match event {
-            Event::MainEventsCleared => window.request_redraw(),
+            Event::MainEventsCleared => {
+                if applied_cursor.0 == CursorGrab::Locked {
+                    center_cursor(&window);
+                }
+                window.request_redraw()
+            }
             Event::WindowEvent {
                 ref event,
                 window_id,
             } if window_id == window.id() => {
-                match event {
-                    WindowEvent::KeyboardInput {
-                        input:
-                            KeyboardInput {
-                                state: ElementState::Pressed,
-                                virtual_keycode: Some(keycode),
-                                ..
-                            },
-                        ..
-                    } => match keycode {
-                        VirtualKeyCode::Space => {
-                            log::debug!("pushing {:?} to event bus", WinEvent::Space);
-                            win_events.push(WinEvent::Space);
-                        }
-                        VirtualKeyCode::W | VirtualKeyCode::Up => {
-                            log::debug!("pushing {:?} to event bus", WinEvent::Up);
-                            win_events.push(WinEvent::Up);
-                        }
-                        VirtualKeyCode::A | VirtualKeyCode::Left => {
-                            log::debug!("pushing {:?} to event bus", WinEvent::Left);
-                            win_events.push(WinEvent::Left);
-                        }
-                        VirtualKeyCode::S | VirtualKeyCode::Down => {
-                            log::debug!("pushing {:?} to event bus", WinEvent::Down);
-                            win_events.push(WinEvent::Down);
-                        }
-                        VirtualKeyCode::D | VirtualKeyCode::Right => {
-                            log::debug!("pushing {:?} to event bus", WinEvent::Right);
-                            win_events.push(WinEvent::Right);
-                        }
-                        VirtualKeyCode::LShift => {
-                            log::debug!("pushing {:?} to event bus", WinEvent::LShift);
-                            win_events.push(WinEvent::LShift);
-                        }
-                        _ => {
-                            log::debug!("event not mapped: {:?}", keycode);
-                        }
-                    },
-                    _ => {}
-                };
+                if let Some(win_event) = map_window_event(event, modifiers) {
+                    log::debug!("pushing {:?} to event bus", win_event);
+                    win_events.push(win_event);
+                }
 
                 match event {
-                    WindowEvent::CloseRequested
-                    | WindowEvent::KeyboardInput {
+                    WindowEvent::CloseRequested => {
+                        log::debug!("changing control flow to exit");
+                        *control_flow = ControlFlow::Exit
+                    }
+                    WindowEvent::KeyboardInput {
                         input:
                             KeyboardInput {
                                 state: ElementState::Pressed,
@@ -84,27 +164,28 @@ pub fn run(
                             },
                         ..
                     } => {
-                        log::debug!("changing control flow to exit");
-                        *control_flow = ControlFlow::Exit
+                        // the first Esc frees a captured cursor, the next one exits
+                        let released = with_cursor(&global_state, |cursor| {
+                            let captured = cursor.is_captured() && !cursor.is_suspended();
+                            cursor.suspend();
+                            captured
+                        });
+                        if released != Some(true) {
+                            log::debug!("changing control flow to exit");
+                            *control_flow = ControlFlow::Exit
+                        }
                     }
-                    WindowEvent::Resized(physical_size) => {
-                        log::debug!(
-                            "pushing resize ({:?}) to event bus",
-                            WinEvent::Resize(physical_size.width, physical_size.height)
-                        );
-                        win_events
-                            .push(WinEvent::Resize(physical_size.width, physical_size.height));
+                    WindowEvent::Focused(false) => {
+                        with_cursor(&global_state, Cursor::suspend);
                     }
-                    WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
-                        log::debug!(
-                            "pushing resize ({:?}) to event bus",
-                            WinEvent::Resize(new_inner_size.width, new_inner_size.height)
-                        );
-                        win_events.push(WinEvent::Resize(
-                            new_inner_size.width,
-                            new_inner_size.height,
-                        ));
+                    WindowEvent::Focused(true)
+                    | WindowEvent::MouseInput {
+                        state: ElementState::Pressed,
+                        ..
+                    } => {
+                        with_cursor(&global_state, Cursor::restore);
                     }
+                    WindowEvent::ModifiersChanged(state) => modifiers = *state,
                     _ => {}
                 }
             }
@@ -119,6 +200,41 @@ pub fn run(
             _ => {}
         };
 
-        game_ticker.tick(&mut global_state, win_events);
+        if let Err(err) = game_ticker.tick(&mut global_state, win_events) {
+            log::error!("game ticker failed: {}", err);
+            game_ticker.save_recording();
+            result = Err(err);
+            *control_flow = ControlFlow::Exit;
+            return;
+        }
+
+        if let Some(effective) = with_cursor(&global_state, |cursor| cursor.effective()) {
+            if effective != applied_cursor {
+                apply_cursor(&window, effective);
+                applied_cursor = effective;
+            }
+        }
+
+        if last_overlay.elapsed() >= std::time::Duration::from_millis(500) {
+            last_overlay = instant::Instant::now();
+            let profiler = global_state.world.read_resource::<profiler::Profiler>();
+            if profiler.enabled && profiler.overlay {
+                let mut text = format!("{} | {}", title, profiler.overlay_text());
+                if let Some(render) = global_state.render.as_ref() {
+                    let stats = render.stats();
+                    text.push_str(&format!(" | {}/{} visible", stats.visible, stats.actors));
+                }
+                window.set_title(&text);
+            }
+        }
+
+        if !global_state.is_running() {
+            log::debug!("no scene left, changing control flow to exit");
+            *control_flow = ControlFlow::Exit
+        }
     });
+
+    game_ticker.finish(&global_state);
+
+    result
 }
@@ -0,0 +1,245 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error;
+use crate::event::WinEvent;
+
+/// Input received by the game ticker on a tick, ticks without input are
+/// merged into the frame before them.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecordedFrame {
+    pub frame: u64,
+    /// Time since the recording started.
+    pub time: time::Duration,
+    /// Time the game ticker advanced by on this frame's ticks.
+    pub frame_time: time::Duration,
+    /// Only input events, see `WinEvent::is_input`.
+    pub events: Vec<WinEvent>,
+}
+
+/// Everything needed to replay a session: the input stream, the ticker's
+/// step settings and the `GameRng` seed.
+///
+/// Files hold one RON value per line, the settings followed by every frame,
+/// so that a `Recorder` can append new frames without rewriting the file.
+#[derive(Clone, Debug)]
+pub struct Recording {
+    pub seed: u64,
+    pub fixed_step: time::Duration,
+    pub max_steps: u32,
+    pub frames: Vec<RecordedFrame>,
+}
+
+impl Recording {
+    pub fn new(seed: u64, fixed_step: time::Duration, max_steps: u32) -> Self {
+        Self {
+            seed,
+            fixed_step,
+            max_steps,
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> error::Result<Self> {
+        let path = path.as_ref();
+        let src = std::fs::read_to_string(path).map_err(|err| asset_error(path, err.into()))?;
+
+        let mut lines = src.lines().filter(|line| !line.trim().is_empty());
+        let header: Header = parse_line(path, lines.next().unwrap_or_default())?;
+        let frames = lines
+            .map(|line| parse_line(path, line))
+            .collect::<error::Result<_>>()?;
+
+        Ok(Self {
+            seed: header.seed,
+            fixed_step: header.fixed_step,
+            max_steps: header.max_steps,
+            frames,
+        })
+    }
+
+    /// Writes the settings and every frame, replacing the file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> error::Result<()> {
+        let path = path.as_ref();
+        let header = Header {
+            seed: self.seed,
+            fixed_step: self.fixed_step,
+            max_steps: self.max_steps,
+        };
+
+        let mut src = to_line(path, &header)?;
+        src += &frames_to_lines(path, &self.frames)?;
+        write_file(path, &src, false)
+    }
+}
+
+// first line of a recording file
+#[derive(Serialize, Deserialize)]
+struct Header {
+    seed: u64,
+    fixed_step: time::Duration,
+    max_steps: u32,
+}
+
+fn asset_error(path: &Path, err: anyhow::Error) -> error::Error {
+    error::Error::asset(&path.to_string_lossy(), err)
+}
+
+fn parse_line<T: serde::de::DeserializeOwned>(path: &Path, line: &str) -> error::Result<T> {
+    ron::from_str(line).map_err(|err| asset_error(path, err.into()))
+}
+
+fn to_line<T: Serialize>(path: &Path, value: &T) -> error::Result<String> {
+    ron::to_string(value)
+        .map(|line| line + "\n")
+        .map_err(|err| asset_error(path, err.into()))
+}
+
+fn frames_to_lines(path: &Path, frames: &[RecordedFrame]) -> error::Result<String> {
+    frames.iter().map(|frame| to_line(path, frame)).collect()
+}
+
+fn write_file(path: &Path, src: &str, append: bool) -> error::Result<()> {
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .append(append)
+        .truncate(!append)
+        .open(path)
+        .and_then(|mut file| file.write_all(src.as_bytes()))
+        .map_err(|err| asset_error(path, err.into()))
+}
+
+/// Appends every tick of the game ticker to a recording.
+///
+/// New frames are appended to `path` every `save_interval`, when finished and
+/// when dropped, so a panicking or failing session still leaves its input behind.
+pub struct Recorder {
+    pub recording: Recording,
+    pub path: Option<PathBuf>,
+    pub save_interval: time::Duration,
+    start: Option<instant::Instant>,
+    last_save: Option<instant::Instant>,
+    // frames already written to `path`
+    saved_frames: usize,
+}
+
+impl Recorder {
+    pub fn new(recording: Recording, path: Option<PathBuf>) -> Self {
+        Self {
+            recording,
+            path,
+            save_interval: time::Duration::from_secs(5),
+            start: None,
+            last_save: None,
+            saved_frames: 0,
+        }
+    }
+
+    /// Non-input events are dropped, replays take them from the live window.
+    pub fn record(&mut self, frame_time: time::Duration, events: &[WinEvent]) {
+        let start = *self.start.get_or_insert_with(instant::Instant::now);
+        let events: Vec<_> = events
+            .iter()
+            .filter(|event| event.is_input())
+            .cloned()
+            .collect();
+
+        // A tick without input only adds time, adding it to the previous frame
+        // replays the same steps unless the ticker would have dropped time,
+        // which it only does past max steps. Saved frames are left untouched.
+        let unsaved = self.recording.frames.len() > self.saved_frames;
+        let max_frame_time = self.recording.fixed_step * self.recording.max_steps;
+        match self.recording.frames.last_mut() {
+            Some(last)
+                if events.is_empty()
+                    && unsaved
+                    && last.frame_time + frame_time < max_frame_time =>
+            {
+                last.frame_time += frame_time;
+            }
+            _ => self.recording.frames.push(RecordedFrame {
+                frame: self.recording.frames.len() as u64,
+                time: start.elapsed(),
+                frame_time,
+                events,
+            }),
+        }
+
+        let last_save = *self.last_save.get_or_insert(start);
+        if last_save.elapsed() >= self.save_interval {
+            self.save();
+        }
+    }
+
+    /// Appends the frames recorded since the last save to `path` if set,
+    /// the first save creates the file.
+    pub fn save(&mut self) {
+        self.last_save = Some(instant::Instant::now());
+        let path = match &self.path {
+            Some(path) if self.saved_frames != self.recording.frames.len() => path,
+            _ => return,
+        };
+
+        let result = if self.saved_frames == 0 {
+            self.recording.save(path)
+        } else {
+            frames_to_lines(path, &self.recording.frames[self.saved_frames..])
+                .and_then(|src| write_file(path, &src, true))
+        };
+        match result {
+            Ok(_) => {
+                self.saved_frames = self.recording.frames.len();
+                log::debug!("{} frames saved to {}", self.saved_frames, path.display());
+            }
+            Err(err) => log::error!("failed to save recording: {}", err),
+        }
+    }
+
+    /// Saves the last frames, called once the app stops running.
+    pub fn finish(&mut self) {
+        self.save();
+        if let Some(path) = &self.path {
+            log::info!(
+                "{} frames recorded to {}",
+                self.recording.frames.len(),
+                path.display()
+            );
+        }
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        self.save();
+    }
+}
+
+/// Hands the recorded frames back to the game ticker, one per tick.
+pub struct Player {
+    recording: Recording,
+    next: usize,
+}
+
+impl Player {
+    pub fn new(recording: Recording) -> Self {
+        Self { recording, next: 0 }
+    }
+
+    pub fn recording(&self) -> &Recording {
+        &self.recording
+    }
+
+    pub fn next_frame(&mut self) -> Option<&RecordedFrame> {
+        let frame = self.recording.frames.get(self.next)?;
+        self.next += 1;
+        Some(frame)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.recording.frames.len()
+    }
+}
@@ -0,0 +1,24 @@
+use std::path::Path;
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::error;
+
+/// Reads and parses a RON file, failures are reported as `Error::Asset`.
+pub fn load<T: DeserializeOwned, P: AsRef<Path>>(path: P) -> error::Result<T> {
+    let path = path.as_ref();
+    let to_error = |err: anyhow::Error| error::Error::asset(&path.to_string_lossy(), err);
+
+    let src = std::fs::read_to_string(path).map_err(|err| to_error(err.into()))?;
+    ron::from_str(&src).map_err(|err| to_error(err.into()))
+}
+
+/// Writes `value` as pretty printed RON, replacing the file.
+pub fn save<T: Serialize, P: AsRef<Path>>(path: P, value: &T) -> error::Result<()> {
+    let path = path.as_ref();
+    let to_error = |err: anyhow::Error| error::Error::asset(&path.to_string_lossy(), err);
+
+    let src = ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default())
+        .map_err(|err| to_error(err.into()))?;
+    std::fs::write(path, src).map_err(|err| to_error(err.into()))
+}
@@ -1,10 +1,45 @@
-use super::state;
-use specs::prelude::*;
+use super::{error, profiler::SystemsBuilder, state};
 
 pub trait Scene {
-    fn setup(&mut self, global_state: &mut state::State);
+    fn setup(&mut self, global_state: &mut state::State) -> error::Result<()>;
     fn setup_systems<'a, 'b>(
         &mut self,
-        dispatcher_builder: DispatcherBuilder<'a, 'b>,
-    ) -> DispatcherBuilder<'a, 'b>;
+        dispatcher_builder: SystemsBuilder<'a, 'b>,
+    ) -> SystemsBuilder<'a, 'b>;
+
+    /// Called after setup, when the scene becomes the running one.
+    fn on_enter(&mut self, _global_state: &mut state::State) {}
+    /// Called when the scene is popped or replaced. World contents are kept
+    /// unless the scene removes them, e.g. with `State::clear_entities`.
+    fn on_exit(&mut self, _global_state: &mut state::State) {}
+    /// Called when another scene is pushed on top of this one.
+    fn on_pause(&mut self, _global_state: &mut state::State) {}
+    /// Called when the scene on top of this one is popped.
+    fn on_resume(&mut self, _global_state: &mut state::State) {}
+}
+
+pub enum Transition {
+    Push(Box<dyn Scene + Send + Sync>),
+    Pop,
+    Replace(Box<dyn Scene + Send + Sync>),
+}
+
+/// Scene changes requested by systems, they are applied after the current step.
+#[derive(Default)]
+pub struct SceneTransitions {
+    pub transitions: Vec<Transition>,
+}
+
+impl SceneTransitions {
+    pub fn push(&mut self, scene: Box<dyn Scene + Send + Sync>) {
+        self.transitions.push(Transition::Push(scene));
+    }
+
+    pub fn pop(&mut self) {
+        self.transitions.push(Transition::Pop);
+    }
+
+    pub fn replace(&mut self, scene: Box<dyn Scene + Send + Sync>) {
+        self.transitions.push(Transition::Replace(scene));
+    }
 }
@@ -1,6 +1,16 @@
+use crate::actor;
+use crate::camera;
+use crate::delta_time;
 use crate::ecs;
+use crate::error;
+use crate::event;
+use crate::game_clock;
+use crate::plugin;
+use crate::profiler::{self, SystemsBuilder};
 use crate::renderer;
+use crate::rng;
 use crate::scene;
+use crate::timer;
 use specs::rayon::ThreadPool;
 use specs::rayon::ThreadPoolBuilder;
 use specs::WorldExt;
@@ -9,50 +19,282 @@ use std::sync::Arc;
 pub struct State {
     pub world: specs::World,
     pub ecs_thread_pool: Arc<ThreadPool>,
-    pub render: renderer::render::Render,
-    pub scene: Option<Box<dyn scene::Scene>>,
+    pub render: Option<renderer::render::Render>,
+    headless_size: (u32, u32),
+    plugins: Vec<Box<dyn plugin::Plugin>>,
+    // swap the buffers of every registered events channel
+    event_updaters: Vec<fn(&specs::World)>,
+    // the running scene is the last one
+    scenes: Vec<SceneEntry>,
+}
+
+struct SceneEntry {
+    scene: Box<dyn scene::Scene>,
+    dispatcher: specs::Dispatcher<'static, 'static>,
+}
+
+#[derive(Clone, Debug)]
+pub struct StateConfig {
+    /// Number of threads used to dispatch systems.
+    pub threads: usize,
+    /// Surface size reported to scenes when there is no window.
+    pub headless_size: (u32, u32),
+    pub profiler: profiler::ProfilerConfig,
+    /// Seed of the `GameRng` resource, random if None.
+    pub seed: Option<u64>,
+}
+
+impl Default for StateConfig {
+    fn default() -> Self {
+        Self {
+            threads: 8,
+            headless_size: (800, 600),
+            profiler: profiler::ProfilerConfig::default(),
+            seed: None,
+        }
+    }
 }
 
 impl State {
-    // TODO: let it configurable
-    const MAX_THREADS: usize = 8;
+    pub fn new(
+        scene: Box<dyn scene::Scene>,
+        window: &winit::window::Window,
+    ) -> error::Result<Self> {
+        let render =
+            renderer::render::Render::new(window, &renderer::render::RenderConfig::default())?;
+        let mut this = Self::with_config(Some(render), &StateConfig::default())?;
+        this.push_scene(scene)?;
 
-    pub fn new(scene: Box<dyn scene::Scene>, window: &winit::window::Window) -> Self {
+        Ok(this)
+    }
+
+    /// Builds a state without window or surface, systems still run but nothing is drawn.
+    pub fn new_headless(scene: Box<dyn scene::Scene>) -> error::Result<Self> {
+        let mut this = Self::with_config(None, &StateConfig::default())?;
+        this.push_scene(scene)?;
+
+        Ok(this)
+    }
+
+    /// Builds a state with no scene, plugins are expected to be added before pushing one.
+    pub fn with_config(
+        render: Option<renderer::render::Render>,
+        config: &StateConfig,
+    ) -> error::Result<Self> {
         let thread_pool = Arc::new(
             ThreadPoolBuilder::new()
-                .num_threads(Self::MAX_THREADS)
+                .num_threads(config.threads)
                 .thread_name(|i| format!("rayon-voxel-{}", i))
-                .build()
-                .unwrap(),
+                .build()?,
         );
 
         let mut this = Self {
             world: specs::World::new(),
             ecs_thread_pool: thread_pool,
-            render: renderer::render::Render::new(window),
-            scene: None,
+            render,
+            headless_size: config.headless_size,
+            plugins: Vec::new(),
+            event_updaters: Vec::new(),
+            scenes: Vec::new(),
         };
+        this.world.register::<actor::Actor>();
+        this.world.register::<camera::CameraBundle>();
+        this.world.insert(scene::SceneTransitions::default());
+        this.world.insert(game_clock::GameClock::default());
+        this.world.insert(delta_time::DeltaTime::default());
+        this.world.insert(profiler::Profiler::new(&config.profiler));
+        this.world
+            .insert(config.seed.map(rng::GameRng::new).unwrap_or_default());
+        this.world.register::<timer::Timer>();
+        this.world.insert(timer::Timers::default());
+        this.add_event::<timer::TimerFinished>();
 
-        this.setup(scene);
+        Ok(this)
+    }
 
-        this
+    pub fn size(&self) -> (u32, u32) {
+        self.render
+            .as_ref()
+            .map(|render| render.size)
+            .unwrap_or(self.headless_size)
     }
 
-    fn setup(&mut self, mut scene: Box<dyn scene::Scene>) {
-        scene.setup(self);
-        self.scene = Some(scene);
+    pub fn is_headless(&self) -> bool {
+        self.render.is_none()
     }
 
-    pub fn setup_global_system<'a, 'b>(
-        &self,
-        dispatcher: specs::DispatcherBuilder<'a, 'b>,
-    ) -> specs::DispatcherBuilder<'a, 'b> {
+    /// Sets the plugin up, its systems only reach scenes pushed afterwards.
+    pub fn add_plugin(&mut self, mut plugin: Box<dyn plugin::Plugin>) -> error::Result<()> {
+        plugin.setup(self)?;
+        self.plugins.push(plugin);
+
+        Ok(())
+    }
+
+    /// Inserts an `Events<T>` channel, its buffers are swapped after every step.
+    pub fn add_event<T: Send + Sync + 'static>(&mut self) {
+        if self.world.has_value::<event::Events<T>>() {
+            return;
+        }
+
+        self.world.insert(event::Events::<T>::default());
+        self.event_updaters.push(|world| {
+            world.write_resource::<event::Events<T>>().update();
+        });
+    }
+
+    /// Swaps the buffers of every channel registered with `add_event`.
+    pub fn update_events(&mut self) {
+        for update in &self.event_updaters {
+            update(&self.world);
+        }
+    }
+
+    /// Records an engine phase that started at `start` and ends now.
+    pub fn profile(&self, name: &str, start: instant::Instant) {
+        self.world
+            .read_resource::<profiler::Profiler>()
+            .record(name, start);
+    }
+
+    /// False once every scene has been popped.
+    pub fn is_running(&self) -> bool {
+        !self.scenes.is_empty()
+    }
+
+    /// Dispatches the running scene's systems, paused scenes don't run.
+    pub fn dispatch(&mut self) {
+        if let Some(entry) = self.scenes.last_mut() {
+            entry.dispatcher.dispatch(&self.world);
+        }
+    }
+
+    /// Pauses the running scene and makes `scene` the running one.
+    /// If `scene` fails to set up, the paused scene is resumed.
+    pub fn push_scene(&mut self, scene: Box<dyn scene::Scene>) -> error::Result<()> {
+        if let Some(mut paused) = self.scenes.pop() {
+            paused.scene.on_pause(self);
+            self.scenes.push(paused);
+        }
+
+        match self.enter(scene) {
+            Ok(entry) => {
+                self.scenes.push(entry);
+                Ok(())
+            }
+            Err(err) => {
+                self.resume_running();
+                Err(err)
+            }
+        }
+    }
+
+    /// Exits the running scene and resumes the one below it.
+    pub fn pop_scene(&mut self) -> Option<Box<dyn scene::Scene>> {
+        let mut exited = self.scenes.pop()?;
+        exited.scene.on_exit(self);
+
+        self.resume_running();
+
+        Some(exited.scene)
+    }
+
+    fn resume_running(&mut self) {
+        if let Some(mut resumed) = self.scenes.pop() {
+            resumed.scene.on_resume(self);
+            self.scenes.push(resumed);
+        }
+    }
+
+    /// Exits the running scene and makes `scene` the running one.
+    /// If `scene` fails to set up, the exited scene is put back and resumed.
+    pub fn replace_scene(
+        &mut self,
+        scene: Box<dyn scene::Scene>,
+    ) -> error::Result<Option<Box<dyn scene::Scene>>> {
+        let mut exited = self.scenes.pop();
+        if let Some(exited) = exited.as_mut() {
+            exited.scene.on_exit(self);
+        }
+
+        match self.enter(scene) {
+            Ok(entry) => {
+                self.scenes.push(entry);
+                Ok(exited.map(|exited| exited.scene))
+            }
+            Err(err) => {
+                self.scenes.extend(exited);
+                self.resume_running();
+                Err(err)
+            }
+        }
+    }
+
+    /// Applies the transitions systems requested through `SceneTransitions`.
+    pub fn apply_scene_transitions(&mut self) -> error::Result<()> {
+        let transitions = std::mem::take(
+            &mut self
+                .world
+                .entry::<scene::SceneTransitions>()
+                .or_insert_with(Default::default)
+                .transitions,
+        );
+
+        for transition in transitions {
+            match transition {
+                scene::Transition::Push(scene) => self.push_scene(scene)?,
+                scene::Transition::Pop => {
+                    self.pop_scene();
+                }
+                scene::Transition::Replace(scene) => {
+                    self.replace_scene(scene)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Deletes every entity, scenes may call it on exit to drop their contents.
+    pub fn clear_entities(&mut self) {
+        self.world.delete_all();
+        self.world.maintain();
+    }
+
+    fn enter(&mut self, mut scene: Box<dyn scene::Scene>) -> error::Result<SceneEntry> {
+        scene.setup(self)?;
+        let dispatcher = self.build_dispatcher(scene.as_mut());
+        scene.on_enter(self);
+
+        Ok(SceneEntry { scene, dispatcher })
+    }
+
+    fn build_dispatcher(
+        &mut self,
+        scene: &mut dyn scene::Scene,
+    ) -> specs::Dispatcher<'static, 'static> {
+        let mut dispatcher_builder = SystemsBuilder::new(
+            specs::DispatcherBuilder::new().with_pool(self.ecs_thread_pool.clone()),
+        );
+        dispatcher_builder = self.setup_global_system(dispatcher_builder);
+        dispatcher_builder = scene.setup_systems(dispatcher_builder);
+
+        let mut dispatcher = dispatcher_builder.build();
+        dispatcher.setup(&mut self.world);
         dispatcher
-            .with(
-                ecs::systems::delta_time::DeltaTimeSys {},
-                "delta_time_sys",
-                &[],
-            )
-            .with_barrier()
+    }
+
+    // GameClock and DeltaTime are advanced by the game ticker on every step,
+    // engine and plugin systems run before the scene ones.
+    pub fn setup_global_system<'a, 'b>(
+        &mut self,
+        mut dispatcher: SystemsBuilder<'a, 'b>,
+    ) -> SystemsBuilder<'a, 'b> {
+        dispatcher = dispatcher.with(ecs::systems::timer::TimerSys::default(), "timer_sys", &[]);
+        for plugin in self.plugins.iter_mut() {
+            dispatcher = plugin.setup_systems(dispatcher);
+        }
+
+        dispatcher.with_barrier()
     }
 }
@@ -1,5 +1,9 @@
-use super::{event, state};
+use super::{error, event, state};
 
 pub trait Ticker {
-    fn tick(&mut self, global_state: &mut state::State, win_events: Vec<event::WinEvent>);
+    fn tick(
+        &mut self,
+        global_state: &mut state::State,
+        win_events: Vec<event::WinEvent>,
+    ) -> error::Result<()>;
 }
@@ -0,0 +1,304 @@
+use specs::{Component, DenseVecStorage, Entity, LazyUpdate};
+use std::collections::BTreeMap;
+use std::time;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimerMode {
+    /// Finishes once and stays finished.
+    Once,
+    /// Finishes every time the duration elapses.
+    Repeating,
+    /// Starts ready, `try_use` restarts it and it finishes once ready again.
+    Cooldown,
+}
+
+/// Countdown advanced by the game clock, it stops while the clock is paused.
+#[derive(Clone, Component, Debug)]
+pub struct Timer {
+    pub duration: time::Duration,
+    pub mode: TimerMode,
+    elapsed: time::Duration,
+    finished: bool,
+    paused: bool,
+    // times it finished during the last tick
+    times_finished: u32,
+}
+
+impl Timer {
+    pub fn new(duration: time::Duration, mode: TimerMode) -> Self {
+        Self {
+            duration,
+            mode,
+            elapsed: time::Duration::ZERO,
+            finished: mode == TimerMode::Cooldown,
+            paused: false,
+            times_finished: 0,
+        }
+    }
+
+    pub fn once(duration: time::Duration) -> Self {
+        Self::new(duration, TimerMode::Once)
+    }
+
+    pub fn repeating(duration: time::Duration) -> Self {
+        Self::new(duration, TimerMode::Repeating)
+    }
+
+    pub fn cooldown(duration: time::Duration) -> Self {
+        Self::new(duration, TimerMode::Cooldown)
+    }
+
+    /// Advances the timer and returns how many times it finished.
+    pub fn tick(&mut self, dt: time::Duration) -> u32 {
+        self.times_finished = 0;
+        if self.paused {
+            return 0;
+        }
+
+        match self.mode {
+            TimerMode::Once | TimerMode::Cooldown => {
+                if self.finished {
+                    return 0;
+                }
+                self.elapsed += dt;
+                if self.elapsed >= self.duration {
+                    self.elapsed = self.duration;
+                    self.finished = true;
+                    self.times_finished = 1;
+                }
+            }
+            TimerMode::Repeating => {
+                self.elapsed += dt;
+                if self.duration.is_zero() {
+                    self.elapsed = time::Duration::ZERO;
+                    self.times_finished = 1;
+                }
+                while !self.duration.is_zero() && self.elapsed >= self.duration {
+                    self.elapsed -= self.duration;
+                    self.times_finished += 1;
+                }
+            }
+        }
+
+        self.times_finished
+    }
+
+    /// Once timers are done, cooldowns are ready and repeating ones never finish.
+    pub fn finished(&self) -> bool {
+        self.finished
+    }
+
+    pub fn just_finished(&self) -> bool {
+        self.times_finished > 0
+    }
+
+    pub fn times_finished(&self) -> u32 {
+        self.times_finished
+    }
+
+    pub fn elapsed(&self) -> time::Duration {
+        self.elapsed
+    }
+
+    pub fn remaining(&self) -> time::Duration {
+        self.duration.saturating_sub(self.elapsed)
+    }
+
+    /// Restarts a ready timer, returns false if it is still running.
+    pub fn try_use(&mut self) -> bool {
+        if !self.finished {
+            return false;
+        }
+
+        self.reset();
+        true
+    }
+
+    pub fn reset(&mut self) {
+        self.elapsed = time::Duration::ZERO;
+        self.finished = false;
+        self.times_finished = 0;
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TimerId(u64);
+
+/// Sent through `Events<TimerFinished>` every time a timer finishes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimerFinished {
+    Scheduled(TimerId),
+    Entity(Entity),
+}
+
+/// Deferred world changes run when a scheduled timer finishes.
+pub type TimerCallback = Box<dyn FnMut(&LazyUpdate) + Send + Sync>;
+
+struct Scheduled {
+    timer: Timer,
+    callback: Option<TimerCallback>,
+}
+
+/// Timers not attached to any entity, finished once timers are dropped.
+#[derive(Default)]
+pub struct Timers {
+    next_id: u64,
+    // ticked in scheduling order, which keeps callbacks and events deterministic
+    timers: BTreeMap<TimerId, Scheduled>,
+}
+
+impl Timers {
+    pub fn schedule(&mut self, timer: Timer) -> TimerId {
+        self.insert(timer, None)
+    }
+
+    pub fn schedule_with<F>(&mut self, timer: Timer, callback: F) -> TimerId
+    where
+        F: FnMut(&LazyUpdate) + Send + Sync + 'static,
+    {
+        self.insert(timer, Some(Box::new(callback)))
+    }
+
+    pub fn after(&mut self, duration: time::Duration) -> TimerId {
+        self.schedule(Timer::once(duration))
+    }
+
+    pub fn every(&mut self, duration: time::Duration) -> TimerId {
+        self.schedule(Timer::repeating(duration))
+    }
+
+    pub fn cancel(&mut self, id: TimerId) -> Option<Timer> {
+        self.timers.remove(&id).map(|scheduled| scheduled.timer)
+    }
+
+    pub fn get(&self, id: TimerId) -> Option<&Timer> {
+        self.timers.get(&id).map(|scheduled| &scheduled.timer)
+    }
+
+    pub fn get_mut(&mut self, id: TimerId) -> Option<&mut Timer> {
+        self.timers
+            .get_mut(&id)
+            .map(|scheduled| &mut scheduled.timer)
+    }
+
+    /// Advances every timer, running callbacks and collecting the finished ones.
+    pub fn tick(&mut self, dt: time::Duration, lazy: &LazyUpdate, finished: &mut Vec<TimerId>) {
+        for (id, scheduled) in self.timers.iter_mut() {
+            for _ in 0..scheduled.timer.tick(dt) {
+                finished.push(*id);
+                if let Some(callback) = scheduled.callback.as_mut() {
+                    callback(lazy);
+                }
+            }
+        }
+
+        self.timers.retain(|_, scheduled| {
+            scheduled.timer.mode != TimerMode::Once || !scheduled.timer.finished()
+        });
+    }
+
+    fn insert(&mut self, timer: Timer, callback: Option<TimerCallback>) -> TimerId {
+        let id = TimerId(self.next_id);
+        self.next_id += 1;
+        self.timers.insert(id, Scheduled { timer, callback });
+
+        id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: time::Duration = time::Duration::from_secs(1);
+
+    fn tick(timers: &mut Timers, dt: time::Duration) -> Vec<TimerId> {
+        let mut finished = Vec::new();
+        timers.tick(dt, &LazyUpdate::default(), &mut finished);
+        finished
+    }
+
+    #[test]
+    fn once_finishes_a_single_time() {
+        let mut timer = Timer::once(SECOND);
+        assert_eq!(timer.tick(SECOND / 2), 0);
+        assert_eq!(timer.tick(SECOND), 1);
+        assert!(timer.finished());
+        assert_eq!(timer.remaining(), time::Duration::ZERO);
+        assert_eq!(timer.tick(SECOND), 0);
+        assert!(!timer.just_finished());
+    }
+
+    #[test]
+    fn repeating_finishes_every_duration() {
+        let mut timer = Timer::repeating(SECOND);
+        assert_eq!(timer.tick(SECOND * 5 / 2), 2);
+        assert_eq!(timer.elapsed(), SECOND / 2);
+        assert_eq!(timer.tick(SECOND / 2), 1);
+        assert!(!timer.finished());
+    }
+
+    #[test]
+    fn paused_timers_keep_their_time() {
+        let mut timer = Timer::once(SECOND);
+        timer.pause();
+        assert_eq!(timer.tick(SECOND * 2), 0);
+        assert_eq!(timer.elapsed(), time::Duration::ZERO);
+
+        timer.resume();
+        assert_eq!(timer.tick(SECOND), 1);
+    }
+
+    #[test]
+    fn cooldowns_start_ready() {
+        let mut timer = Timer::cooldown(SECOND);
+        assert!(timer.try_use());
+        assert!(!timer.try_use());
+        timer.tick(SECOND);
+        assert!(timer.try_use());
+    }
+
+    #[test]
+    fn scheduled_timers_finish_in_scheduling_order() {
+        let mut timers = Timers::default();
+        let ids: Vec<TimerId> = (0..8).map(|_| timers.every(SECOND)).collect();
+
+        assert_eq!(tick(&mut timers, SECOND), ids);
+    }
+
+    #[test]
+    fn finished_once_timers_are_dropped() {
+        let mut timers = Timers::default();
+        let once = timers.after(SECOND);
+        let repeating = timers.every(SECOND);
+
+        assert_eq!(tick(&mut timers, SECOND), [once, repeating]);
+        assert!(timers.get(once).is_none());
+        assert_eq!(tick(&mut timers, SECOND), [repeating]);
+    }
+
+    #[test]
+    fn cancelled_and_paused_timers_dont_finish() {
+        let mut timers = Timers::default();
+        let cancelled = timers.after(SECOND);
+        let paused = timers.after(SECOND);
+        assert!(timers.cancel(cancelled).is_some());
+        timers.get_mut(paused).unwrap().pause();
+
+        assert!(tick(&mut timers, SECOND).is_empty());
+        assert!(timers.cancel(cancelled).is_none());
+        assert!(timers.get(paused).is_some());
+    }
+}
@@ -0,0 +1,250 @@
+use std::time;
+
+use specs::prelude::*;
+use specs::Component;
+use voxel::{delta_time, error, game_clock, profiler::SystemsBuilder, scene, state};
+
+#[derive(Component, Debug)]
+struct Position(f32);
+
+#[derive(Component, Debug)]
+struct Velocity(f32);
+
+struct MoveSys;
+
+impl<'a> System<'a> for MoveSys {
+    type SystemData = (
+        Read<'a, delta_time::DeltaTime>,
+        ReadStorage<'a, Velocity>,
+        WriteStorage<'a, Position>,
+    );
+
+    fn run(&mut self, (dt, velocities, mut positions): Self::SystemData) {
+        let dt = dt.dt.as_secs_f32();
+        for (velocity, position) in (&velocities, &mut positions).join() {
+            position.0 += velocity.0 * dt;
+        }
+    }
+}
+
+// pops itself once `steps` steps have run
+struct StopSys {
+    steps: u64,
+}
+
+impl<'a> System<'a> for StopSys {
+    type SystemData = (
+        Read<'a, game_clock::GameClock>,
+        Write<'a, scene::SceneTransitions>,
+    );
+
+    fn run(&mut self, (clock, mut transitions): Self::SystemData) {
+        if clock.frame() == self.steps {
+            transitions.pop();
+        }
+    }
+}
+
+struct Scene {
+    stop_after: Option<u64>,
+}
+
+impl scene::Scene for Scene {
+    fn setup(&mut self, global_state: &mut state::State) -> error::Result<()> {
+        global_state.world.register::<Position>();
+        global_state.world.register::<Velocity>();
+        global_state
+            .world
+            .create_entity()
+            .with(Position(0.0))
+            .with(Velocity(2.0))
+            .build();
+
+        Ok(())
+    }
+
+    fn setup_systems<'a, 'b>(
+        &mut self,
+        dispatcher_builder: SystemsBuilder<'a, 'b>,
+    ) -> SystemsBuilder<'a, 'b> {
+        let dispatcher_builder = dispatcher_builder.with(MoveSys, "move_sys", &[]);
+        match self.stop_after {
+            Some(steps) => dispatcher_builder.with(StopSys { steps }, "stop_sys", &[]),
+            None => dispatcher_builder,
+        }
+    }
+
+    fn on_resume(&mut self, global_state: &mut state::State) {
+        global_state
+            .world
+            .entry::<Resumes>()
+            .or_insert_with(Default::default)
+            .0 += 1;
+    }
+}
+
+#[derive(Default)]
+struct Resumes(u32);
+
+// fails to load, the running scene must stay untouched
+struct BrokenScene;
+
+impl scene::Scene for BrokenScene {
+    fn setup(&mut self, _global_state: &mut state::State) -> error::Result<()> {
+        Err(error::Error::InvalidConfig("broken scene"))
+    }
+
+    fn setup_systems<'a, 'b>(
+        &mut self,
+        dispatcher_builder: SystemsBuilder<'a, 'b>,
+    ) -> SystemsBuilder<'a, 'b> {
+        dispatcher_builder
+    }
+}
+
+fn build(stop_after: Option<u64>) -> voxel::App {
+    voxel::AppBuilder::new()
+        .with_headless(true)
+        .with_threads(2)
+        .with_fixed_step(time::Duration::from_millis(10))
+        .with_seed(7)
+        .build(Box::new(Scene { stop_after }))
+        .unwrap()
+}
+
+#[test]
+fn steps_systems_without_window() {
+    let mut app = build(None);
+    assert!(app.is_headless());
+
+    for _ in 0..50 {
+        app.tick().unwrap();
+    }
+
+    let world = &app.global_state.world;
+    let clock = world.read_resource::<game_clock::GameClock>();
+    assert_eq!(clock.frame(), 50);
+    assert_eq!(clock.game_time(), time::Duration::from_millis(500));
+
+    let positions = world.read_storage::<Position>();
+    let position = positions.join().next().unwrap();
+    assert!((position.0 - 1.0).abs() < 1e-4, "{:?}", position);
+}
+
+#[test]
+fn paused_clock_freezes_systems() {
+    let mut app = build(None);
+    app.global_state
+        .world
+        .write_resource::<game_clock::GameClock>()
+        .pause();
+
+    for _ in 0..10 {
+        app.tick().unwrap();
+    }
+
+    let positions = app.global_state.world.read_storage::<Position>();
+    assert_eq!(positions.join().next().unwrap().0, 0.0);
+}
+
+#[test]
+fn run_returns_once_the_scene_pops() {
+    let app = build(Some(3));
+    app.run().unwrap();
+}
+
+#[test]
+fn failed_scene_setups_keep_the_running_scene() {
+    let mut app = build(None);
+
+    assert!(app.global_state.push_scene(Box::new(BrokenScene)).is_err());
+    assert!(app
+        .global_state
+        .replace_scene(Box::new(BrokenScene))
+        .is_err());
+    assert!(app.global_state.is_running());
+    assert_eq!(app.global_state.world.read_resource::<Resumes>().0, 2);
+
+    // the original scene's systems still run
+    for _ in 0..10 {
+        app.tick().unwrap();
+    }
+    let positions = app.global_state.world.read_storage::<Position>();
+    assert!((positions.join().next().unwrap().0 - 0.2).abs() < 1e-4);
+}
+
+#[test]
+fn zero_step_settings_are_rejected() {
+    use voxel::game_ticker::GameTicker;
+
+    assert!(GameTicker::new(time::Duration::ZERO, 5).is_err());
+    assert!(GameTicker::new(time::Duration::from_millis(10), 0).is_err());
+}
+
+#[test]
+fn builder_rejects_zero_step_settings() {
+    let scene = || Box::new(Scene { stop_after: None });
+    let builder = || voxel::AppBuilder::new().with_headless(true);
+
+    assert!(builder().with_max_steps(0).build(scene()).is_err());
+    assert!(builder()
+        .with_fixed_step(time::Duration::ZERO)
+        .build(scene())
+        .is_err());
+}
+
+fn recording_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("voxel-{}-{}.ron", name, std::process::id()))
+}
+
+#[test]
+fn dropped_recorders_save_their_frames() {
+    use voxel::event::WinEvent;
+    use voxel::replay::{Recorder, Recording};
+
+    let path = recording_path("dropped");
+    let mut recorder = Recorder::new(
+        Recording::new(7, time::Duration::from_millis(10), 5),
+        Some(path.clone()),
+    );
+    recorder.record(time::Duration::from_millis(10), &[]);
+    recorder.record(time::Duration::from_millis(10), &[WinEvent::Focused(true)]);
+    drop(recorder);
+
+    let recording = Recording::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(recording.seed, 7);
+    assert_eq!(recording.frames.len(), 2);
+}
+
+#[test]
+fn recorders_merge_ticks_without_input_and_append_new_frames() {
+    use voxel::event::WinEvent;
+    use voxel::replay::{Recorder, Recording};
+
+    let path = recording_path("appended");
+    let ms = time::Duration::from_millis;
+    let mut recorder = Recorder::new(Recording::new(7, ms(10), 5), Some(path.clone()));
+    recorder.record(ms(4), &[WinEvent::Focused(true)]);
+    // merged into the frame above, redraws aren't input
+    recorder.record(ms(4), &[]);
+    recorder.record(ms(4), &[WinEvent::Redraw]);
+    recorder.save();
+    let saved = std::fs::read_to_string(&path).unwrap();
+
+    // saved frames are never merged into
+    recorder.record(ms(4), &[]);
+    recorder.record(ms(30), &[]);
+    // merging would exceed max steps
+    recorder.record(ms(30), &[]);
+    recorder.finish();
+
+    let src = std::fs::read_to_string(&path).unwrap();
+    let recording = Recording::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(src.starts_with(&saved));
+    let frame_times: Vec<_> = recording.frames.iter().map(|f| f.frame_time).collect();
+    assert_eq!(frame_times, [ms(12), ms(34), ms(30)]);
+    assert_eq!(recording.frames[0].events.len(), 1);
+    assert!(recording.frames[1].events.is_empty());
+}